@@ -0,0 +1,85 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum Errors {
+    #[msg("Limit amount must be greater than zero and no more than what is left.")]
+    InvalidLimitAmount,
+    #[msg("Ask price must be greater than zero.")]
+    InvalidAskPrice,
+    #[msg("An NFT limit must offer the single token of a zero decimal mint.")]
+    InvalidNft,
+    #[msg("Payment account is not the vault account of the ask asset.")]
+    InvalidAskAsset,
+    #[msg("An overflow occurs.")]
+    Overflow,
+    #[msg("Order price must be greater than zero.")]
+    InvalidOrderPrice,
+    #[msg("Order quantities must be greater than zero.")]
+    InvalidOrderQuantity,
+    #[msg("Lot sizes must be greater than zero.")]
+    InvalidLotSize,
+    #[msg("Coin and price currency mints must differ.")]
+    InvalidMarketMints,
+    #[msg("Market vaults must be owned by the market's vault signer.")]
+    InvalidMarketVault,
+    #[msg("NFT is not a verified member of the collection.")]
+    InvalidCollection,
+    #[msg("NFT inventory is full.")]
+    NftInventoryFull,
+    #[msg("NFT is not held by the vault.")]
+    NftNotInVault,
+    #[msg("Amount exceeds the depositor's recorded balance.")]
+    InsufficientBalance,
+    #[msg("Share amount must be greater than zero and no more than the share supply.")]
+    InvalidShareAmount,
+    #[msg("Funds are still locked.")]
+    FundsLocked,
+    #[msg("Lock must start no later than its cliff, and the cliff no later than its end.")]
+    InvalidLock,
+    #[msg("Vault is already locked.")]
+    LockAlreadySet,
+    #[msg("Multisig is already set.")]
+    MultisigAlreadySet,
+    #[msg("Multisig is not set.")]
+    MultisigNotSet,
+    #[msg("Multisig needs between 1 and 10 distinct owners.")]
+    InvalidMultisigOwners,
+    #[msg("Threshold must be between 1 and the number of owners.")]
+    InvalidMultisigThreshold,
+    #[msg("Signer is not a multisig owner.")]
+    NotMultisigOwner,
+    #[msg("Owner has already approved the transfer.")]
+    AlreadyApproved,
+    #[msg("Transfer has not reached the approval threshold.")]
+    NotEnoughApprovals,
+    #[msg("Transfer amount must be greater than zero.")]
+    InvalidTransferAmount,
+    #[msg("Transfer is for another asset.")]
+    InvalidTransferAsset,
+    #[msg("Owner withdrawals need multisig approval.")]
+    MultisigRequired,
+    #[msg("Token program must be the token program or Token-2022, and own the mint.")]
+    InvalidTokenProgram,
+    #[msg("Token account does not belong to the expected mint, owner or token program.")]
+    InvalidTokenAccount,
+    #[msg("Vault still holds funds, withdraw them before closing.")]
+    VaultNotEmpty,
+    #[msg("Bump seed of a PDA could not be found.")]
+    MissingBump,
+    #[msg("NFT mint must have zero decimals and a supply of one.")]
+    InvalidNftMint,
+    #[msg("Vault collection is already set.")]
+    CollectionAlreadySet,
+    #[msg("NFTs are moved with the NFT instructions.")]
+    NftNotFungible,
+    #[msg("Balance or NFT inventory account of the asset must be provided.")]
+    MissingAssetAccount,
+    #[msg("Mint does not match the asset of the limit.")]
+    InvalidLimitAsset,
+    #[msg("Vault still holds balances or open positions.")]
+    PositionsOpen,
+    #[msg("Only other depositors' balances of this vault can be refunded, and only to their owner.")]
+    InvalidRefund,
+    #[msg("A locked grant must open a new balance of someone other than the vault owner.")]
+    InvalidGrant,
+}
@@ -1,20 +1,27 @@
 use anchor_lang::{prelude::*, system_program};
 use anchor_spl::{
-    associated_token::AssociatedToken,
+    associated_token::{
+        create, get_associated_token_address_with_program_id, AssociatedToken, Create,
+    },
     metadata::{
         create_master_edition_v3, create_metadata_accounts_v3, CreateMasterEditionV3,
-        CreateMetadataAccountsV3, MetadataAccount,
+        CreateMetadataAccountsV3, Metadata, MetadataAccount,
     },
     token::{
-        initialize_mint2, InitializeMint2, Mint, Token, TokenAccount, Transfer as SplTransfer,
+        burn, close_account, mint_to, Burn, CloseAccount, Mint, MintTo, Token, TokenAccount,
+        Transfer as SplTransfer,
     },
-    dex::{close_open_orders, self, NewOrderV3, Dex, CancelOrderV2, InitializeMarket as InitializeMarketV1},
+    dex::{self, NewOrderV3, Dex, CancelOrderV2, InitializeMarket as InitializeMarketV1},
 };
 use std::num::NonZeroU64;
 
-use anchor_spl::dex::serum_dex::{matching::{Side, OrderType}, instruction::SelfTradeBehavior };
+use anchor_spl::dex::serum_dex::{self, matching::{Side, OrderType}, instruction::SelfTradeBehavior };
+
+use mpl_token_metadata::state::{Creator, DataV2};
 
-use mpl_token_metadata::state::DataV2;
+pub mod error;
+pub mod token_interface;
+use error::Errors;
 
 
 declare_id!("7YKyo13HtdB823RiWHacDR74wc7VeU8vkMZGJDP2nSUB");
@@ -34,20 +41,21 @@ pub mod deposit {
     pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
         let deposit_account = &mut ctx.accounts.deposit_account;
         deposit_account.deposit_auth = *ctx.accounts.deposit_auth.key;
-        ctx.accounts.deposit_account.auth_bump = *ctx.bumps.get("pda_auth").unwrap();
+        deposit_account.auth_bump = *ctx.bumps.get("pda_auth").ok_or(Errors::MissingBump)?;
+        deposit_account.sol_vault_bump = *ctx.bumps.get("sol_vault").ok_or(Errors::MissingBump)?;
         Ok(())
     }
 
     //methods for depositing and withdrawing native tokens
-    pub fn deposit_native(ctx: Context<DepositNative>, amount: u64) -> Result<()> {
-        let deposit_account = &mut ctx.accounts.deposit_account;
-        let deposit_auth = &ctx.accounts.deposit_auth;
+    // credits the beneficiary's balance, the depositor's own for a plain deposit. A funder can open a
+    // beneficiary's balance as a vesting grant by passing a lock.
+    pub fn deposit_native(ctx: Context<DepositNative>, amount: u64, lock: Option<VestingLock>) -> Result<()> {
+        let deposit_account = &ctx.accounts.deposit_account;
+        let depositor = &ctx.accounts.depositor;
         let sys_program = &ctx.accounts.system_program;
 
-        deposit_account.sol_vault_bump = ctx.bumps.get("sol_vault").copied();
-
         let cpi_accounts = system_program::Transfer {
-            from: deposit_auth.to_account_info(),
+            from: depositor.to_account_info(),
             to: ctx.accounts.sol_vault.to_account_info(),
         };
 
@@ -55,10 +63,32 @@ pub mod deposit {
 
         system_program::transfer(cpi, amount)?;
 
+        let balance = &mut ctx.accounts.balance;
+        balance.deposit_account = deposit_account.key();
+        balance.owner = ctx.accounts.beneficiary.key();
+        balance.mint = None;
+        if let Some(lock) = lock {
+            balance.set_lock(lock, &deposit_account.deposit_auth)?;
+        }
+        let before = balance.amount;
+        balance.credit(amount)?;
+        let after = balance.amount;
+
+        ctx.accounts.deposit_account.track_balance(before, after);
+
         Ok(())
     }
 
     pub fn withdraw_native(ctx: Context<WithdrawNative>, amount: u64) -> Result<()> {
+        ctx.accounts.deposit_account.check_direct_withdrawal(ctx.accounts.depositor.key)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let lock = ctx.accounts.deposit_account.lock_for(&ctx.accounts.balance);
+        let before = ctx.accounts.balance.amount;
+        ctx.accounts.balance.withdraw(amount, &lock, now)?;
+        let after = ctx.accounts.balance.amount;
+        ctx.accounts.deposit_account.track_balance(before, after);
+
         let sys_program = &ctx.accounts.system_program;
         let deposit_account = &ctx.accounts.deposit_account;
         let pda_auth = &mut ctx.accounts.pda_auth;
@@ -66,13 +96,13 @@ pub mod deposit {
 
         let cpi_accounts = system_program::Transfer {
             from: sol_vault.to_account_info(),
-            to: ctx.accounts.deposit_auth.to_account_info(),
+            to: ctx.accounts.depositor.to_account_info(),
         };
 
         let seeds = &[
             b"sol_vault",
             pda_auth.to_account_info().key.as_ref(),
-            &[deposit_account.sol_vault_bump.unwrap()],
+            &[deposit_account.sol_vault_bump],
         ];
 
         let signer = &[&seeds[..]];
@@ -85,29 +115,95 @@ pub mod deposit {
     }
 
     //methods for depositing and withdrawing fungible SPL tokens
-    pub fn deposit_spl(ctx: Context<DepositSpl>, amount: u64) -> Result<()> {
-        let cpi_accounts = SplTransfer {
-            from: ctx.accounts.from_token_acct.to_account_info(),
-            to: ctx.accounts.to_token_acct.to_account_info(),
-            authority: ctx.accounts.deposit_auth.to_account_info(),
-        };
+    // accepts legacy and Token-2022 mints. Like deposit_native, it can fund a vesting grant.
+    pub fn deposit_spl(ctx: Context<DepositSpl>, amount: u64, lock: Option<VestingLock>) -> Result<()> {
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let token_mint = ctx.accounts.token_mint.to_account_info();
+        let to_token_acct = ctx.accounts.to_token_acct.to_account_info();
+
+        token_interface::check_token_program(&token_program)?;
+        let decimals = token_interface::mint_decimals(&token_mint, &token_program)?;
+        // NFTs are tracked in the inventory instead of a balance
+        if token_interface::is_nft(&token_mint, &token_program)? {
+            return Err(Errors::NftNotFungible.into());
+        }
 
-        let cpi = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        if to_token_acct.data_is_empty() {
+            let cpi_accounts = Create {
+                payer: ctx.accounts.depositor.to_account_info(),
+                associated_token: to_token_acct.clone(),
+                authority: ctx.accounts.pda_auth.to_account_info(),
+                mint: token_mint.clone(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: token_program.clone(),
+            };
 
-        anchor_spl::token::transfer(cpi, amount)?;
+            let cpi = CpiContext::new(ctx.accounts.associated_token_program.to_account_info(), cpi_accounts);
+
+            create(cpi)?;
+        }
+
+        token_interface::token_amount(
+            &ctx.accounts.from_token_acct,
+            token_mint.key,
+            Some(ctx.accounts.depositor.key),
+            &token_program,
+        )?;
+        let vault_before = token_interface::token_amount(&to_token_acct, token_mint.key, Some(ctx.accounts.pda_auth.key), &token_program)?;
+
+        token_interface::transfer_checked(
+            &token_program,
+            &ctx.accounts.from_token_acct,
+            &token_mint,
+            &to_token_acct,
+            &ctx.accounts.depositor,
+            amount,
+            decimals,
+            &[],
+        )?;
+
+        // a transfer fee extension withholds part of the amount, only what arrived is recorded
+        let vault_after = token_interface::token_amount(&to_token_acct, token_mint.key, None, &token_program)?;
+        let received = vault_after.checked_sub(vault_before).ok_or(Errors::Overflow)?;
+
+        let balance = &mut ctx.accounts.balance;
+        balance.deposit_account = ctx.accounts.deposit_account.key();
+        balance.owner = ctx.accounts.beneficiary.key();
+        balance.mint = Some(token_mint.key());
+        if let Some(lock) = lock {
+            balance.set_lock(lock, &ctx.accounts.deposit_account.deposit_auth)?;
+        }
+        let before = balance.amount;
+        balance.credit(received)?;
+        let after = balance.amount;
+
+        ctx.accounts.deposit_account.track_balance(before, after);
 
         Ok(())
     }
 
 
     pub fn withdraw_spl(ctx: Context<WithdrawSpl>, amount: u64) -> Result<()> {
-        let deposit_account = &ctx.accounts.deposit_account;
+        ctx.accounts.deposit_account.check_direct_withdrawal(ctx.accounts.depositor.key)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let lock = ctx.accounts.deposit_account.lock_for(&ctx.accounts.balance);
+        let before = ctx.accounts.balance.amount;
+        ctx.accounts.balance.withdraw(amount, &lock, now)?;
+        let after = ctx.accounts.balance.amount;
+        ctx.accounts.deposit_account.track_balance(before, after);
+
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let token_mint = ctx.accounts.token_mint.to_account_info();
+
+        token_interface::check_token_program(&token_program)?;
+        let decimals = token_interface::mint_decimals(&token_mint, &token_program)?;
+        if token_interface::is_nft(&token_mint, &token_program)? {
+            return Err(Errors::NftNotFungible.into());
+        }
+        token_interface::token_amount(&ctx.accounts.to_token_acct, token_mint.key, None, &token_program)?;
 
-        let cpi_accounts = SplTransfer {
-            from: ctx.accounts.from_token_acct.to_account_info(),
-            to: ctx.accounts.to_token_acct.to_account_info(),
-            authority: ctx.accounts.pda_auth.to_account_info(),
-        };
+        let deposit_account = &ctx.accounts.deposit_account;
 
         let seeds = &[
             b"auth",
@@ -117,329 +213,2240 @@ pub mod deposit {
 
         let signer = &[&seeds[..]];
 
-        let cpi = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            cpi_accounts,
+        // with a transfer fee the withdrawer receives the amount less the fee
+        token_interface::transfer_checked(
+            &token_program,
+            &ctx.accounts.from_token_acct,
+            &token_mint,
+            &ctx.accounts.to_token_acct,
+            &ctx.accounts.pda_auth,
+            amount,
+            decimals,
             signer,
-        );
+        )
+    }
 
-        anchor_spl::token::transfer(cpi, amount)?;
+    // closes the deposit once every position has been settled. Balances of other depositors do not keep the
+    // vault open: each is passed as remaining accounts followed by its owner's wallet, and for tokens still
+    // held by the vault token account, the owner's token account and the mint, then refunded and closed with
+    // its rent returned to the owner. Any SOL left in the vault after that is not owed to anyone and is swept to
+    // deposit_auth. The vault's token accounts are passed as well, must be empty, and are closed with all
+    // rent returned to deposit_auth, as is the NFT inventory.
+    pub fn close_deposit<'info>(ctx: Context<'_, '_, '_, 'info, CloseDeposit<'info>>) -> Result<()> {
+        ctx.accounts.deposit_account.check_direct_withdrawal(ctx.accounts.deposit_auth.key)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let deposit_key = ctx.accounts.deposit_account.key();
+        let deposit_auth = ctx.accounts.deposit_auth.to_account_info();
+        let pda_auth = ctx.accounts.pda_auth.to_account_info();
+        let sol_vault = ctx.accounts.sol_vault.to_account_info();
+        let sys_program = ctx.accounts.system_program.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let token_2022_program = ctx.accounts.token_2022_program.as_ref().map(|program| program.to_account_info());
+
+        let auth_seeds = &[
+            b"auth",
+            deposit_key.as_ref(),
+            &[ctx.accounts.deposit_account.auth_bump],
+        ];
+        let auth_signer = &[&auth_seeds[..]];
 
-        Ok(())
-    }
+        let sol_vault_seeds = &[
+            b"sol_vault",
+            pda_auth.key.as_ref(),
+            &[ctx.accounts.deposit_account.sol_vault_bump],
+        ];
+        let sol_vault_signer = &[&sol_vault_seeds[..]];
 
-    pub fn initialize_market(ctx: Context<InitializeMarket>, vault_signer_nonce: u64) -> Result<()> {
-        let dex_program = ctx.accounts.dex_program.to_account_info();
-        let coin_lot_size: u64 = 10;
-        let pc_lot_size: u64 = 10;
-        //let vault_signer_nonce: u64 = 1;
-        let pc_dust_threshold: u64 = 5;
-       
-        dex::initialize_market(
-            CpiContext::new(
-            dex_program,
-            dex::InitializeMarket{
-                market: ctx.accounts.market.to_account_info().clone(),
-                coin_mint: ctx.accounts.coin_mint.to_account_info().clone(),
-                pc_mint: ctx.accounts.pc_mint.to_account_info().clone(),
-                coin_vault: ctx.accounts.coin_vault.to_account_info().clone(),
-                pc_vault: ctx.accounts.pc_vault.to_account_info().clone(),
-                bids: ctx.accounts.bids.to_account_info().clone(),
-                asks: ctx.accounts.asks.to_account_info().clone(),
-                req_q: ctx.accounts.req_q.to_account_info().clone(),
-                event_q: ctx.accounts.event_q.to_account_info().clone(),
-                rent: ctx.accounts.rent.to_account_info().clone(),
+        // token accounts and mints may belong to the legacy token program or Token-2022
+        let program_of = |account: &AccountInfo<'info>| -> Result<AccountInfo<'info>> {
+            if account.owner == token_program.key {
+                return Ok(token_program.clone());
             }
-        ),
-        coin_lot_size,
-        pc_lot_size,
-        vault_signer_nonce,
-        pc_dust_threshold)
-    }
+            match &token_2022_program {
+                Some(token_2022_program) if account.owner == token_2022_program.key => Ok(token_2022_program.clone()),
+                _ => Err(Errors::InvalidTokenProgram.into()),
+            }
+        };
 
-    pub fn new_order(ctx: Context<NewOrder>, limit_price:NonZeroU64) -> Result<()> {
-        let dex_program = ctx.accounts.dex_program.to_account_info();
+        let mut remaining_accounts = ctx.remaining_accounts.iter();
+        let mut vault_token_accts = Vec::new();
+        let mut refunded = 0;
+        while let Some(account) = remaining_accounts.next() {
+            if account.owner != ctx.program_id {
+                vault_token_accts.push(account);
+                continue;
+            }
 
-        let side:anchor_spl::dex::serum_dex::matching::Side = Side::Ask;
-        let max_coin_qty = NonZeroU64::new(1000000000000000000).unwrap();
-        let max_native_pc_qty_including_fees= NonZeroU64::new(1000000000000000000).unwrap();
-        let self_trade_behavior = SelfTradeBehavior::DecrementTake;
-        let order_type = OrderType::Limit;
-        let client_order_id = 1;
-        let limit = 100u16;
-       
-        // CODING CHALLENGE:
-        // get this to compile and you will have completed the create order.
-        /*
-        let accounts: NewOrderV3<'static> = ctx;
+            // the owner's funds only leave through the withdrawal instructions, where the lock and multisig
+            // apply, but their emptied balance accounts are closed here too
+            let balance = UserBalance::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+            let wallet = remaining_accounts.next().ok_or(Errors::InvalidRefund)?;
+            let owner_funds = balance.owner == *deposit_auth.key && balance.amount > 0;
+            if balance.deposit_account != deposit_key || owner_funds || *wallet.key != balance.owner {
+                return Err(Errors::InvalidRefund.into());
+            }
+            // a grant is only refunded once it has fully vested
+            let lock = ctx.accounts.deposit_account.lock_for(&balance);
+            if balance.withdrawable(&lock, now) < balance.amount {
+                return Err(Errors::FundsLocked.into());
+            }
 
-        let cpi = CpiContext::new(dex_program,accounts.into());
+            if balance.amount > 0 {
+                match balance.mint {
+                    None => {
+                        let cpi_accounts = system_program::Transfer {
+                            from: sol_vault.clone(),
+                            to: wallet.clone(),
+                        };
+
+                        let cpi = CpiContext::new_with_signer(sys_program.clone(), cpi_accounts, sol_vault_signer);
+
+                        system_program::transfer(cpi, balance.amount)?;
+                    }
+                    Some(mint) => {
+                        let from_token_acct = remaining_accounts.next().ok_or(Errors::InvalidRefund)?;
+                        let to_token_acct = remaining_accounts.next().ok_or(Errors::InvalidRefund)?;
+                        let token_mint = remaining_accounts.next().ok_or(Errors::InvalidRefund)?;
+                        if *token_mint.key != mint {
+                            return Err(Errors::InvalidRefund.into());
+                        }
+
+                        let token_program = program_of(token_mint)?;
+                        if *from_token_acct.key != get_associated_token_address_with_program_id(pda_auth.key, &mint, token_program.key) {
+                            return Err(Errors::InvalidTokenAccount.into());
+                        }
+                        token_interface::token_amount(to_token_acct, &mint, Some(wallet.key), &token_program)?;
+                        let decimals = token_interface::mint_decimals(token_mint, &token_program)?;
+
+                        token_interface::transfer_checked(
+                            &token_program,
+                            from_token_acct,
+                            token_mint,
+                            to_token_acct,
+                            &pda_auth,
+                            balance.amount,
+                            decimals,
+                            auth_signer,
+                        )?;
+                    }
+                }
+                refunded += 1;
+            }
 
-        dex::new_order_v3(cpi, side, limit_price, max_coin_qty, max_native_pc_qty_including_fees, self_trade_behavior, order_type, client_order_id, limit)?;
-        
-        Ok(())
-        */
+            // the balance's rent goes back to its owner
+            let rent = account.lamports();
+            **wallet.try_borrow_mut_lamports()? = wallet.lamports().checked_add(rent).ok_or(Errors::Overflow)?;
+            **account.try_borrow_mut_lamports()? = 0;
+            account.assign(&system_program::ID);
+            account.realloc(0, false)?;
+        }
 
-        /// CODING CHALLENGE:
-        /// get this to compile and you will have completed the create order.
-        dex::new_order_v3(
-            CpiContext::new(
-            dex_program,
-            dex::NewOrderV3{
-                market: ctx.accounts.market.to_account_info().clone(),
-                open_orders: ctx.accounts.open_orders.to_account_info().clone(),
-                request_queue: ctx.accounts.request_queue.to_account_info().clone(),
-                order_payer_token_account: ctx.accounts.order_payer_token_account.to_account_info(),
-                open_orders_authority: ctx.accounts.open_orders_authority.to_account_info().clone(),
-                event_queue: ctx.accounts.event_queue.to_account_info().clone(),
-                market_bids: ctx.accounts.market_bids.to_account_info().clone(),
-                market_asks: ctx.accounts.market_asks.to_account_info().clone(),
-                coin_vault: ctx.accounts.coin_vault.to_account_info().clone(),
-                pc_vault: ctx.accounts.pc_vault.to_account_info().clone(),
-                token_program: ctx.accounts.token_program.to_account_info(),
-                rent: ctx.accounts.rent.to_account_info().clone(),
-            }
-        ),
-        side,
-        limit_price,
-        max_coin_qty,
-        max_native_pc_qty_including_fees,
-        self_trade_behavior,
-        order_type,
-        client_order_id,
-        limit)
-    }
+        let open_positions = &mut ctx.accounts.deposit_account.open_positions;
+        open_positions.balances -= refunded;
+        if !open_positions.is_empty() {
+            return Err(Errors::PositionsOpen.into());
+        }
 
-    /// CODING CHALLENGE: complete this instruction handler
-    /// pass in the variables needed to cancel and order, 
-    /// replace "......" with the correct variables
-    pub fn cancel_order(ctx: Context<CancelOrder>) -> Result<()> {
+        let unaccounted = sol_vault.lamports();
+        if unaccounted > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: sol_vault.clone(),
+                to: deposit_auth.clone(),
+            };
 
-        //dex::cancel_order_v2(program_id, market, market_bids, market_asks, open_orders_account, open_orders_account_owner, event_queue, side, order_id);
+            let cpi = CpiContext::new_with_signer(sys_program.clone(), cpi_accounts, sol_vault_signer);
 
-        //Ok(())
-        let side:anchor_spl::dex::serum_dex::matching::Side = Side::Ask;
-        let order_id: u128 = 1;
-        let dex_program = ctx.accounts.dex_program.to_account_info();
+            system_program::transfer(cpi, unaccounted)?;
+        }
 
-        dex::cancel_order_v2(
-            CpiContext::new(
-            dex_program,
-            dex::CancelOrderV2{
-                 market:ctx.accounts.market.to_account_info().clone() ,
-                 market_bids: ctx.accounts.market_bids.to_account_info().clone() ,
-                 market_asks: ctx.accounts.market_asks.to_account_info().clone() ,
-                 open_orders: ctx.accounts.open_orders.to_account_info().clone() ,
-                 open_orders_authority: ctx.accounts.open_orders_authority.to_account_info().clone(),
-                 event_queue: ctx.accounts.event_queue.to_account_info().clone()
+        for token_acct in vault_token_accts {
+            let token_program = program_of(token_acct)?;
+
+            let mint = token_interface::token_mint(token_acct, &token_program)?;
+            if token_interface::token_amount(token_acct, &mint, Some(pda_auth.key), &token_program)? > 0 {
+                return Err(Errors::VaultNotEmpty.into());
             }
-        ),
-        side,
-        order_id)
-    }
 
-}
+            token_interface::close_account(
+                &token_program,
+                token_acct,
+                &deposit_auth,
+                &pda_auth,
+                auth_signer,
+            )?;
+        }
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(init, payer = deposit_auth, space = DepositBase::LEN)]
-    pub deposit_account: Account<'info, DepositBase>,
-    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump)]
-    /// CHECK: no need to check this.
-    pub pda_auth: UncheckedAccount<'info>,
-    #[account(mut)]
-    pub deposit_auth: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct DepositNative<'info> {
-    #[account(mut, has_one = deposit_auth)]
-    pub deposit_account: Account<'info, DepositBase>,
-    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
-    /// CHECK: no need to check this.
-    pub pda_auth: UncheckedAccount<'info>,
-    #[account(mut, seeds = [b"sol_vault", pda_auth.key().as_ref()], bump)]
-    pub sol_vault: SystemAccount<'info>,
-    #[account(mut)]
-    pub deposit_auth: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    // locks the owner's recorded balances and NFTs. Grants to other depositors carry their own lock, set
+    // with the deposit that funds them. A lock cannot be changed once set.
+    pub fn set_lock(ctx: Context<SetLock>, lock: VestingLock) -> Result<()> {
+        let deposit_account = &mut ctx.accounts.deposit_account;
 
-#[derive(Accounts)]
-pub struct WithdrawNative<'info> {
-    #[account(has_one = deposit_auth)]
-    pub deposit_account: Account<'info, DepositBase>,
-    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
-    /// CHECK: no need to check this.
-    pub pda_auth: UncheckedAccount<'info>,
-    #[account(mut, seeds = [b"sol_vault", pda_auth.key().as_ref()], bump = deposit_account.sol_vault_bump.unwrap())]
-    pub sol_vault: SystemAccount<'info>,
-    #[account(mut)]
-    pub deposit_auth: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        if deposit_account.lock.is_some() {
+            return Err(Errors::LockAlreadySet.into());
+        }
+        lock.check()?;
 
-#[derive(Accounts)]
-pub struct DepositSpl<'info> {
-    #[account(has_one = deposit_auth)]
-    pub deposit_account: Account<'info, DepositBase>,
-    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
-    /// CHECK: no need to check this.
-    pub pda_auth: UncheckedAccount<'info>,
-    #[account(mut)]
-    pub deposit_auth: Signer<'info>,
-    #[account(
-        init_if_needed,
-        associated_token::mint = token_mint,
-        payer = deposit_auth,
-        associated_token::authority = pda_auth,
-    )]
-    pub to_token_acct: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub from_token_acct: Account<'info, TokenAccount>,
-    pub token_mint: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
-}
+        deposit_account.lock = Some(lock);
 
-#[derive(Accounts)]
-pub struct WithdrawSpl<'info> {
-    #[account(has_one = deposit_auth)]
-    pub deposit_account: Account<'info, DepositBase>,
-    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
-    /// CHECK: no need to check this.
-    pub pda_auth: UncheckedAccount<'info>,
-    #[account(mut)]
-    pub deposit_auth: Signer<'info>,
-    #[account(mut)]
-    pub to_token_acct: Account<'info, TokenAccount>,
-    #[account(mut,
-        associated_token::mint = token_mint,
-        associated_token::authority = pda_auth,
-    )]
-    pub from_token_acct: Account<'info, TokenAccount>,
-    pub token_mint: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
-}
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct InitializeMarket<'info> {
-    pub market: AccountInfo<'info>,
-    pub coin_mint: AccountInfo<'info>,
-    pub pc_mint: AccountInfo<'info>,
-    pub coin_vault: AccountInfo<'info>,
-    pub pc_vault: AccountInfo<'info>,
-    pub bids: AccountInfo<'info>,
-    pub asks: AccountInfo<'info>,
-    pub req_q: AccountInfo<'info>,
-    pub event_q: AccountInfo<'info>,
-    pub rent: AccountInfo<'info>,
-    pub dex_program: Program<'info, Dex>,
-}
+    // restricts NFT deposits to verified members of `collection`. Like a lock, it cannot be changed once set.
+    pub fn set_nft_collection(ctx: Context<SetNftCollection>, collection: Pubkey) -> Result<()> {
+        let deposit_account = &mut ctx.accounts.deposit_account;
 
-#[derive(Accounts)]
-pub struct NewOrder<'info> {
-    pub market: AccountInfo<'info>,
-    pub open_orders: AccountInfo<'info>,
-    pub request_queue: AccountInfo<'info>,
-    pub event_queue: AccountInfo<'info>,
-    pub market_bids: AccountInfo<'info>,
-    pub market_asks: AccountInfo<'info>,
-    // Token account where funds are transferred from for the order. If
-    // posting a bid market A/B, then this is the SPL token account for B.
-    pub order_payer_token_account: Account<'info, TokenAccount>, //AssociatedToken
-    pub open_orders_authority: AccountInfo<'info>,
-    // Also known as the "base" currency. For a given A/B market,
-    // this is the vault for the A mint.
-    pub coin_vault: AccountInfo<'info>,
-    // Also known as the "quote" currency. For a given A/B market,
-    // this is the vault for the B mint.
-    pub pc_vault: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>,
-    pub dex_program: Program<'info,Dex>,
-    pub rent: AccountInfo<'info>,
-}
-/*
-impl From <&mut NewOrder<'static>> for NewOrderV3<'static> {
-    fn from (new_order: &mut NewOrder<'static>) -> Self {
-        NewOrderV3 {
-            market: new_order.market.clone(),
-            open_orders: new_order.open_orders.clone(),
-            request_queue: new_order.request_queue.clone(),
-            order_payer_token_account: new_order.order_payer_token_account.to_account_info(),
-            open_orders_authority: new_order.open_orders_authority.clone(),
-            event_queue: new_order.event_queue.clone(),
-            market_bids: new_order.market_bids.clone(),
-            market_asks: new_order.market_asks.clone(),
-            coin_vault: new_order.coin_vault.clone(),
-            pc_vault: new_order.pc_vault.clone(),
-            token_program: new_order.token_program.to_account_info(),
-            rent: new_order.rent.clone(),
-        }
-    }
-}
-*/
-#[derive(Accounts)]
-pub struct CancelOrder<'info> {
-    pub market: AccountInfo<'info>,
-    pub market_bids: AccountInfo<'info>,
-    pub market_asks: AccountInfo<'info>,
-    pub open_orders: AccountInfo<'info>,
-    pub open_orders_authority: AccountInfo<'info>,
-    pub event_queue: AccountInfo<'info>,
-    pub dex_program: Program<'info, Dex>,
-}
-/*
-impl From <&mut CancelOrder<'static>> for CancelOrderV2<'static> {
-    fn from (cancel_order: &mut CancelOrder<'static>) -> Self {
-        CancelOrderV2 {
-            market: cancel_order.market.clone(),
-            market_bids: cancel_order.market_bids.clone(),
-            market_asks: cancel_order.market_asks.clone(),
-            open_orders: cancel_order.open_orders.clone(),
-            open_orders_authority: cancel_order.open_orders_authority.clone(),
-            event_queue: cancel_order.event_queue.clone()
+        if deposit_account.nft_collection.is_some() {
+            return Err(Errors::CollectionAlreadySet.into());
         }
-    }
-}
-*/
-
-#[derive(Accounts)]
-pub struct UpdateLimit {
 
-}
+        deposit_account.nft_collection = Some(collection);
 
-#[derive(Accounts)]
-pub struct RemoveLimit {
+        Ok(())
+    }
 
-}
+    // puts the owner's balances under M-of-N control: they can then only be withdrawn through approved
+    // pending transfers. Like a lock, the multisig cannot be changed once set.
+    pub fn set_multisig(ctx: Context<SetMultisig>, owners: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        let deposit_account = &mut ctx.accounts.deposit_account;
 
-#[derive(Accounts)]
-pub struct AcceptLimit {
+        if deposit_account.multisig.is_some() {
+            return Err(Errors::MultisigAlreadySet.into());
+        }
+        if owners.is_empty() || owners.len() > Multisig::MAX_OWNERS {
+            return Err(Errors::InvalidMultisigOwners.into());
+        }
+        if owners.iter().enumerate().any(|(i, owner)| owners[..i].contains(owner)) {
+            return Err(Errors::InvalidMultisigOwners.into());
+        }
+        if threshold == 0 || threshold as usize > owners.len() {
+            return Err(Errors::InvalidMultisigThreshold.into());
+        }
 
-}
+        deposit_account.multisig = Some(Multisig {
+            owners,
+            threshold,
+            transfer_count: 0,
+        });
+
+        Ok(())
+    }
+
+    // proposes withdrawing `amount` of the owner's balance (of SOL when `mint` is None) to `to`, a wallet
+    // for SOL or a token account otherwise. With an Nft `asset_type` the vault's NFT of `mint` is withdrawn
+    // instead, executed through the inventory. The proposer's approval is counted.
+    pub fn propose_transfer(ctx: Context<ProposeTransfer>, asset_type: AssetType, mint: Option<Pubkey>, amount: u64, to: Pubkey) -> Result<()> {
+        let proposer = ctx.accounts.proposer.key();
+        let deposit_account_key = ctx.accounts.deposit_account.key();
+        let multisig = ctx
+            .accounts
+            .deposit_account
+            .multisig
+            .as_mut()
+            .ok_or(Errors::MultisigNotSet)?;
+
+        if !multisig.owners.contains(&proposer) {
+            return Err(Errors::NotMultisigOwner.into());
+        }
+        if amount == 0 {
+            return Err(Errors::InvalidTransferAmount.into());
+        }
+        if asset_type == AssetType::Nft {
+            if mint.is_none() {
+                return Err(Errors::InvalidTransferAsset.into());
+            }
+            if amount != 1 {
+                return Err(Errors::InvalidTransferAmount.into());
+            }
+        }
+
+        let pending_transfer = &mut ctx.accounts.pending_transfer;
+        pending_transfer.deposit_account = deposit_account_key;
+        pending_transfer.index = multisig.transfer_count;
+        pending_transfer.proposer = proposer;
+        pending_transfer.asset_type = asset_type;
+        pending_transfer.mint = mint;
+        pending_transfer.amount = amount;
+        pending_transfer.to = to;
+        pending_transfer.approvals = vec![proposer];
+
+        multisig.transfer_count += 1;
+        ctx.accounts.deposit_account.open_positions.transfers += 1;
+
+        Ok(())
+    }
+
+    pub fn approve_transfer(ctx: Context<ApproveTransfer>) -> Result<()> {
+        let owner = ctx.accounts.owner.key();
+        let multisig = ctx
+            .accounts
+            .deposit_account
+            .multisig
+            .as_ref()
+            .ok_or(Errors::MultisigNotSet)?;
+
+        if !multisig.owners.contains(&owner) {
+            return Err(Errors::NotMultisigOwner.into());
+        }
+
+        let pending_transfer = &mut ctx.accounts.pending_transfer;
+        if pending_transfer.approvals.contains(&owner) {
+            return Err(Errors::AlreadyApproved.into());
+        }
+        pending_transfer.approvals.push(owner);
+
+        Ok(())
+    }
+
+    // drops a proposal that is not going to be executed, e.g. one that cannot reach the threshold, so it
+    // no longer keeps the vault open. Any multisig owner can cancel; the rent goes back to the proposer.
+    pub fn cancel_transfer(ctx: Context<CancelTransfer>) -> Result<()> {
+        let owner = ctx.accounts.owner.key();
+        let multisig = ctx
+            .accounts
+            .deposit_account
+            .multisig
+            .as_ref()
+            .ok_or(Errors::MultisigNotSet)?;
+
+        if !multisig.owners.contains(&owner) {
+            return Err(Errors::NotMultisigOwner.into());
+        }
+
+        ctx.accounts.deposit_account.open_positions.transfers -= 1;
+
+        Ok(())
+    }
+
+    // executes an approved SOL transfer out of the owner's balance and closes it
+    pub fn execute_native_transfer(ctx: Context<ExecuteNativeTransfer>) -> Result<()> {
+        let deposit_account = &ctx.accounts.deposit_account;
+        let pending_transfer = &ctx.accounts.pending_transfer;
+
+        deposit_account.check_approved(pending_transfer)?;
+        if pending_transfer.mint.is_some() {
+            return Err(Errors::InvalidTransferAsset.into());
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        let lock = deposit_account.lock_for(&ctx.accounts.balance);
+        let before = ctx.accounts.balance.amount;
+        ctx.accounts.balance.withdraw(pending_transfer.amount, &lock, now)?;
+
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.sol_vault.to_account_info(),
+            to: ctx.accounts.to.to_account_info(),
+        };
+
+        let seeds = &[
+            b"sol_vault",
+            ctx.accounts.pda_auth.key.as_ref(),
+            &[deposit_account.sol_vault_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        let cpi = CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+
+        system_program::transfer(cpi, pending_transfer.amount)?;
+
+        let after = ctx.accounts.balance.amount;
+        let deposit_account = &mut ctx.accounts.deposit_account;
+        deposit_account.track_balance(before, after);
+        deposit_account.open_positions.transfers -= 1;
+
+        Ok(())
+    }
+
+    // executes an approved SPL transfer out of the owner's balance and closes it
+    pub fn execute_spl_transfer(ctx: Context<ExecuteSplTransfer>) -> Result<()> {
+        let deposit_account = &ctx.accounts.deposit_account;
+        let pending_transfer = &ctx.accounts.pending_transfer;
+
+        deposit_account.check_approved(pending_transfer)?;
+        if pending_transfer.asset_type != AssetType::Spl || pending_transfer.mint != Some(ctx.accounts.token_mint.key()) {
+            return Err(Errors::InvalidTransferAsset.into());
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        let lock = deposit_account.lock_for(&ctx.accounts.balance);
+        let before = ctx.accounts.balance.amount;
+        ctx.accounts.balance.withdraw(pending_transfer.amount, &lock, now)?;
+
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let token_mint = ctx.accounts.token_mint.to_account_info();
+
+        token_interface::check_token_program(&token_program)?;
+        let decimals = token_interface::mint_decimals(&token_mint, &token_program)?;
+        token_interface::token_amount(&ctx.accounts.to_token_acct, token_mint.key, None, &token_program)?;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        token_interface::transfer_checked(
+            &token_program,
+            &ctx.accounts.from_token_acct,
+            &token_mint,
+            &ctx.accounts.to_token_acct,
+            &ctx.accounts.pda_auth,
+            pending_transfer.amount,
+            decimals,
+            signer,
+        )?;
+
+        let after = ctx.accounts.balance.amount;
+        let deposit_account = &mut ctx.accounts.deposit_account;
+        deposit_account.track_balance(before, after);
+        deposit_account.open_positions.transfers -= 1;
+
+        Ok(())
+    }
+
+    // executes an approved transfer of one of the vault's NFTs and closes it, along with the vault's now
+    // empty token account
+    pub fn execute_nft_transfer(ctx: Context<ExecuteNftTransfer>) -> Result<()> {
+        let deposit_account = &ctx.accounts.deposit_account;
+        let pending_transfer = &ctx.accounts.pending_transfer;
+
+        deposit_account.check_approved(pending_transfer)?;
+        if pending_transfer.asset_type != AssetType::Nft || pending_transfer.mint != Some(ctx.accounts.nft_mint.key()) {
+            return Err(Errors::InvalidTransferAsset.into());
+        }
+
+        // as with withdraw_nft, an NFT stays in the vault until the lock ends
+        let now = Clock::get()?.unix_timestamp;
+        if let Some(lock) = &deposit_account.lock {
+            if now < lock.end_ts {
+                return Err(Errors::FundsLocked.into());
+            }
+        }
+
+        ctx.accounts.nft_inventory.remove(&ctx.accounts.nft_mint.key())?;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = SplTransfer {
+            from: ctx.accounts.from_token_acct.to_account_info(),
+            to: ctx.accounts.to_token_acct.to_account_info(),
+            authority: ctx.accounts.pda_auth.to_account_info(),
+        };
+
+        let cpi = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+
+        anchor_spl::token::transfer(cpi, 1)?;
+
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.from_token_acct.to_account_info(),
+            destination: ctx.accounts.deposit_auth.to_account_info(),
+            authority: ctx.accounts.pda_auth.to_account_info(),
+        };
+
+        let cpi = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+
+        close_account(cpi)?;
+
+        let held = ctx.accounts.nft_inventory.nfts.len() as u32;
+        let deposit_account = &mut ctx.accounts.deposit_account;
+        deposit_account.open_positions.nfts = held;
+        deposit_account.open_positions.transfers -= 1;
+
+        Ok(())
+    }
+
+    // amount of a recorded balance that can be withdrawn now, returned to the caller
+    pub fn withdrawable(ctx: Context<Withdrawable>) -> Result<u64> {
+        let now = Clock::get()?.unix_timestamp;
+
+        let lock = ctx.accounts.deposit_account.lock_for(&ctx.accounts.balance);
+
+        Ok(ctx.accounts.balance.withdrawable(&lock, now))
+    }
+
+    // deposits into the mint's share pool for receipt tokens proportional to the deposit's share of the
+    // pool. Receipts are transferable and redeem for the underlying through redeem_shares.
+    pub fn deposit_shares(ctx: Context<DepositShares>, amount: u64) -> Result<()> {
+        let share_supply = ctx.accounts.share_mint.supply;
+
+        let share_pool = &mut ctx.accounts.share_pool;
+        share_pool.deposit_account = ctx.accounts.deposit_account.key();
+        share_pool.mint = ctx.accounts.token_mint.key();
+
+        let shares = share_pool.shares_for(amount, share_supply)?;
+        if shares == 0 {
+            return Err(Errors::InvalidShareAmount.into());
+        }
+        let before = share_pool.assets;
+        share_pool.assets = share_pool.assets.checked_add(amount).ok_or(Errors::Overflow)?;
+        let after = share_pool.assets;
+        ctx.accounts.deposit_account.track_pool(before, after);
+
+        let cpi_accounts = SplTransfer {
+            from: ctx.accounts.from_token_acct.to_account_info(),
+            to: ctx.accounts.share_vault.to_account_info(),
+            authority: ctx.accounts.depositor.to_account_info(),
+        };
+
+        let cpi = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+
+        anchor_spl::token::transfer(cpi, amount)?;
+
+        let deposit_account = &ctx.accounts.deposit_account;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.share_mint.to_account_info(),
+            to: ctx.accounts.share_token_acct.to_account_info(),
+            authority: ctx.accounts.pda_auth.to_account_info(),
+        };
+
+        let cpi = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+
+        mint_to(cpi, shares)
+    }
+
+    // burns receipt tokens for their proportional share of the pool
+    pub fn redeem_shares(ctx: Context<RedeemShares>, shares: u64) -> Result<()> {
+        let share_supply = ctx.accounts.share_mint.supply;
+
+        if shares == 0 || shares > share_supply {
+            return Err(Errors::InvalidShareAmount.into());
+        }
+        let share_pool = &mut ctx.accounts.share_pool;
+        let amount = share_pool.assets_for(shares, share_supply);
+        let before = share_pool.assets;
+        share_pool.assets -= amount;
+        let after = share_pool.assets;
+        ctx.accounts.deposit_account.track_pool(before, after);
+
+        let cpi_accounts = Burn {
+            mint: ctx.accounts.share_mint.to_account_info(),
+            from: ctx.accounts.share_token_acct.to_account_info(),
+            authority: ctx.accounts.depositor.to_account_info(),
+        };
+
+        let cpi = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+
+        burn(cpi, shares)?;
+
+        let deposit_account = &ctx.accounts.deposit_account;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = SplTransfer {
+            from: ctx.accounts.share_vault.to_account_info(),
+            to: ctx.accounts.to_token_acct.to_account_info(),
+            authority: ctx.accounts.pda_auth.to_account_info(),
+        };
+
+        let cpi = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+
+        anchor_spl::token::transfer(cpi, amount)
+    }
+
+    // deposits a single NFT, which must be a verified member of the vault's collection when one is set
+    pub fn deposit_nft(ctx: Context<DepositNft>) -> Result<()> {
+        if let Some(collection) = ctx.accounts.deposit_account.nft_collection {
+            match &ctx.accounts.metadata.collection {
+                Some(nft_collection) if nft_collection.verified && nft_collection.key == collection => {}
+                _ => return Err(Errors::InvalidCollection.into()),
+            }
+        }
+
+        let cpi_accounts = SplTransfer {
+            from: ctx.accounts.from_token_acct.to_account_info(),
+            to: ctx.accounts.to_token_acct.to_account_info(),
+            authority: ctx.accounts.deposit_auth.to_account_info(),
+        };
+
+        let cpi = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+
+        anchor_spl::token::transfer(cpi, 1)?;
+
+        let inventory = &mut ctx.accounts.nft_inventory;
+        inventory.deposit_account = ctx.accounts.deposit_account.key();
+        inventory.add(ctx.accounts.nft_mint.key())?;
+        let held = inventory.nfts.len() as u32;
+
+        ctx.accounts.deposit_account.open_positions.nfts = held;
+
+        Ok(())
+    }
+
+    // withdraws an NFT held by the vault and closes the vault's now empty token account
+    pub fn withdraw_nft(ctx: Context<WithdrawNft>) -> Result<()> {
+        ctx.accounts.deposit_account.check_direct_withdrawal(ctx.accounts.deposit_auth.key)?;
+
+        // an NFT cannot vest partially, so it stays in the vault until the lock ends
+        let now = Clock::get()?.unix_timestamp;
+        if let Some(lock) = &ctx.accounts.deposit_account.lock {
+            if now < lock.end_ts {
+                return Err(Errors::FundsLocked.into());
+            }
+        }
+
+        ctx.accounts.nft_inventory.remove(&ctx.accounts.nft_mint.key())?;
+        ctx.accounts.deposit_account.open_positions.nfts = ctx.accounts.nft_inventory.nfts.len() as u32;
+
+        let deposit_account = &ctx.accounts.deposit_account;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = SplTransfer {
+            from: ctx.accounts.from_token_acct.to_account_info(),
+            to: ctx.accounts.to_token_acct.to_account_info(),
+            authority: ctx.accounts.pda_auth.to_account_info(),
+        };
+
+        let cpi = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+
+        anchor_spl::token::transfer(cpi, 1)?;
+
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.from_token_acct.to_account_info(),
+            destination: ctx.accounts.deposit_auth.to_account_info(),
+            authority: ctx.accounts.pda_auth.to_account_info(),
+        };
+
+        let cpi = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+
+        close_account(cpi)
+    }
+
+    pub fn initialize_market(
+        ctx: Context<InitializeMarket>,
+        coin_lot_size: u64,
+        pc_lot_size: u64,
+        vault_signer_nonce: u64,
+        pc_dust_threshold: u64,
+    ) -> Result<()> {
+        if coin_lot_size == 0 || pc_lot_size == 0 {
+            return Err(Errors::InvalidLotSize.into());
+        }
+        if ctx.accounts.coin_mint.key() == ctx.accounts.pc_mint.key() {
+            return Err(Errors::InvalidMarketMints.into());
+        }
+
+        // the dex only accepts vaults owned by the market's vault signer
+        let vault_signer = serum_dex::state::gen_vault_signer_key(
+            vault_signer_nonce,
+            ctx.accounts.market.key,
+            ctx.accounts.dex_program.key,
+        )?;
+        if ctx.accounts.coin_vault.owner != vault_signer || ctx.accounts.pc_vault.owner != vault_signer {
+            return Err(Errors::InvalidMarketVault.into());
+        }
+
+        let market_info = &mut ctx.accounts.market_info;
+        market_info.market = ctx.accounts.market.key();
+        market_info.coin_mint = ctx.accounts.coin_mint.key();
+        market_info.pc_mint = ctx.accounts.pc_mint.key();
+        market_info.coin_lot_size = coin_lot_size;
+        market_info.pc_lot_size = pc_lot_size;
+        market_info.creator = ctx.accounts.payer.key();
+
+        let dex_program = ctx.accounts.dex_program.to_account_info();
+
+        dex::initialize_market(
+            CpiContext::new(
+            dex_program,
+            dex::InitializeMarket{
+                market: ctx.accounts.market.to_account_info().clone(),
+                coin_mint: ctx.accounts.coin_mint.to_account_info().clone(),
+                pc_mint: ctx.accounts.pc_mint.to_account_info().clone(),
+                coin_vault: ctx.accounts.coin_vault.to_account_info().clone(),
+                pc_vault: ctx.accounts.pc_vault.to_account_info().clone(),
+                bids: ctx.accounts.bids.to_account_info().clone(),
+                asks: ctx.accounts.asks.to_account_info().clone(),
+                req_q: ctx.accounts.req_q.to_account_info().clone(),
+                event_q: ctx.accounts.event_q.to_account_info().clone(),
+                rent: ctx.accounts.rent.to_account_info().clone(),
+            }
+        ),
+        coin_lot_size,
+        pc_lot_size,
+        vault_signer_nonce,
+        pc_dust_threshold)
+    }
+
+    pub fn new_order(
+        ctx: Context<NewOrder>,
+        side: OrderSide,
+        limit_price: u64,
+        max_coin_qty: u64,
+        max_native_pc_qty_including_fees: u64,
+        self_trade_behavior: OrderSelfTradeBehavior,
+        order_type: DexOrderType,
+        client_order_id: u64,
+        limit: u16,
+    ) -> Result<()> {
+        // orders at any price could move the owner's funds to a counterparty of their choosing
+        ctx.accounts.deposit_account.check_direct_withdrawal(ctx.accounts.deposit_auth.key)?;
+
+        let dex_program = ctx.accounts.dex_program.to_account_info();
+
+        let limit_price = NonZeroU64::new(limit_price).ok_or(Errors::InvalidOrderPrice)?;
+        let max_coin_qty = NonZeroU64::new(max_coin_qty).ok_or(Errors::InvalidOrderQuantity)?;
+        let max_native_pc_qty_including_fees =
+            NonZeroU64::new(max_native_pc_qty_including_fees).ok_or(Errors::InvalidOrderQuantity)?;
+
+        // pda_auth owns the open orders account and pays for the order out of the vault
+        let deposit_account = &ctx.accounts.deposit_account;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        let payer_before = ctx.accounts.order_payer_token_account.amount;
+
+        dex::new_order_v3(
+            CpiContext::new_with_signer(
+            dex_program,
+            dex::NewOrderV3{
+                market: ctx.accounts.market.to_account_info().clone(),
+                open_orders: ctx.accounts.open_orders.to_account_info().clone(),
+                request_queue: ctx.accounts.request_queue.to_account_info().clone(),
+                order_payer_token_account: ctx.accounts.order_payer_token_account.to_account_info(),
+                open_orders_authority: ctx.accounts.pda_auth.to_account_info(),
+                event_queue: ctx.accounts.event_queue.to_account_info().clone(),
+                market_bids: ctx.accounts.market_bids.to_account_info().clone(),
+                market_asks: ctx.accounts.market_asks.to_account_info().clone(),
+                coin_vault: ctx.accounts.coin_vault.to_account_info().clone(),
+                pc_vault: ctx.accounts.pc_vault.to_account_info().clone(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info().clone(),
+            },
+            signer,
+        ),
+        side.into(),
+        limit_price,
+        max_coin_qty,
+        max_native_pc_qty_including_fees,
+        self_trade_behavior.into(),
+        order_type.into(),
+        client_order_id,
+        limit)?;
+
+        // tokens locked in the open orders account are no longer held for the owner
+        ctx.accounts.order_payer_token_account.reload()?;
+        let paid = payer_before.saturating_sub(ctx.accounts.order_payer_token_account.amount);
+        let now = Clock::get()?.unix_timestamp;
+        let lock = ctx.accounts.deposit_account.lock_for(&ctx.accounts.owner_balance);
+        let before = ctx.accounts.owner_balance.amount;
+        ctx.accounts.owner_balance.debit_unlocked(paid, &lock, now)?;
+        let after = ctx.accounts.owner_balance.amount;
+
+        ctx.accounts.deposit_account.track_balance(before, after);
+
+        Ok(())
+    }
+
+    pub fn cancel_order(ctx: Context<CancelOrder>, side: OrderSide, order_id: u128) -> Result<()> {
+        let dex_program = ctx.accounts.dex_program.to_account_info();
+        let deposit_account = &ctx.accounts.deposit_account;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        dex::cancel_order_v2(
+            CpiContext::new_with_signer(
+            dex_program,
+            dex::CancelOrderV2{
+                 market:ctx.accounts.market.to_account_info().clone() ,
+                 market_bids: ctx.accounts.market_bids.to_account_info().clone() ,
+                 market_asks: ctx.accounts.market_asks.to_account_info().clone() ,
+                 open_orders: ctx.accounts.open_orders.to_account_info().clone() ,
+                 open_orders_authority: ctx.accounts.pda_auth.to_account_info(),
+                 event_queue: ctx.accounts.event_queue.to_account_info().clone()
+            },
+            signer,
+        ),
+        side.into(),
+        order_id)
+    }
+
+    // anchor_spl::dex has no wrapper for cancelling by client order id, so the instruction is built
+    // with serum_dex and invoked directly
+    pub fn cancel_order_by_client_id(ctx: Context<CancelOrder>, client_order_id: u64) -> Result<()> {
+        let ix = serum_dex::instruction::cancel_order_by_client_order_id(
+            ctx.accounts.dex_program.key,
+            ctx.accounts.market.key,
+            ctx.accounts.market_bids.key,
+            ctx.accounts.market_asks.key,
+            ctx.accounts.open_orders.key,
+            ctx.accounts.pda_auth.key,
+            ctx.accounts.event_queue.key,
+            client_order_id,
+        )
+        .map_err(ProgramError::from)?;
+
+        let deposit_account = &ctx.accounts.deposit_account;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        solana_program::program::invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.market.to_account_info(),
+                ctx.accounts.market_bids.to_account_info(),
+                ctx.accounts.market_asks.to_account_info(),
+                ctx.accounts.open_orders.to_account_info(),
+                ctx.accounts.pda_auth.to_account_info(),
+                ctx.accounts.event_queue.to_account_info(),
+                ctx.accounts.dex_program.to_account_info(),
+            ],
+            &[&seeds[..]],
+        )?;
+
+        Ok(())
+    }
+
+    // mints a 1 supply NFT into the vault with pda_auth as mint and update authority. The master edition
+    // takes over the mint authority, so no more tokens can ever be minted.
+    pub fn mint_nft(
+        ctx: Context<MintNft>,
+        name: String,
+        symbol: String,
+        uri: String,
+        seller_fee_basis_points: u16,
+        creators: Vec<NftCreator>,
+    ) -> Result<()> {
+        let deposit_account = &ctx.accounts.deposit_account;
+        let pda_auth = &ctx.accounts.pda_auth;
+
+        // minted NFTs belong to no collection
+        if deposit_account.nft_collection.is_some() {
+            return Err(Errors::InvalidCollection.into());
+        }
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.vault_nft_acct.to_account_info(),
+            authority: pda_auth.to_account_info(),
+        };
+
+        let cpi = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+
+        mint_to(cpi, 1)?;
+
+        // only pda_auth signs, so it is the only creator that can be verified here
+        let creators = if creators.is_empty() {
+            None
+        } else {
+            Some(
+                creators
+                    .into_iter()
+                    .map(|creator| Creator {
+                        address: creator.address,
+                        verified: creator.address == pda_auth.key(),
+                        share: creator.share,
+                    })
+                    .collect(),
+            )
+        };
+
+        let data = DataV2 {
+            name,
+            symbol,
+            uri,
+            seller_fee_basis_points,
+            creators,
+            collection: None,
+            uses: None,
+        };
+
+        let meta_data_cpi = CreateMetadataAccountsV3 {
+            metadata: ctx.accounts.metadata.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            mint_authority: pda_auth.to_account_info(),
+            payer: ctx.accounts.deposit_auth.to_account_info(),
+            update_authority: pda_auth.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+
+        let cpi = CpiContext::new_with_signer(
+            ctx.accounts.metadata_program.to_account_info(),
+            meta_data_cpi,
+            signer,
+        );
+
+        create_metadata_accounts_v3(cpi, data, true, true, None)?;
+
+        let master_edition_cpi = CreateMasterEditionV3 {
+            edition: ctx.accounts.edition.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            update_authority: pda_auth.to_account_info(),
+            mint_authority: pda_auth.to_account_info(),
+            payer: ctx.accounts.deposit_auth.to_account_info(),
+            metadata: ctx.accounts.metadata.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+
+        let cpi = CpiContext::new_with_signer(
+            ctx.accounts.metadata_program.to_account_info(),
+            master_edition_cpi,
+            signer,
+        );
+
+        // no prints of the master edition
+        create_master_edition_v3(cpi, Some(0))?;
+
+        let inventory = &mut ctx.accounts.nft_inventory;
+        inventory.deposit_account = deposit_account.key();
+        inventory.add(ctx.accounts.mint.key())?;
+        let held = inventory.nfts.len() as u32;
+
+        ctx.accounts.deposit_account.open_positions.nfts = held;
+
+        Ok(())
+    }
+
+    // creates the vault's open orders account for a market, owned by pda_auth
+    pub fn init_open_orders(ctx: Context<InitOpenOrders>) -> Result<()> {
+        let deposit_account = &ctx.accounts.deposit_account;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        dex::init_open_orders(CpiContext::new_with_signer(
+            ctx.accounts.dex_program.to_account_info(),
+            dex::InitOpenOrders {
+                open_orders: ctx.accounts.open_orders.to_account_info(),
+                authority: ctx.accounts.pda_auth.to_account_info(),
+                market: ctx.accounts.market.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            },
+            signer,
+        ))?;
+
+        ctx.accounts.deposit_account.open_positions.open_orders += 1;
+
+        Ok(())
+    }
+
+    // moves the vault's free and filled balances out of the market back into the vault
+    pub fn settle_funds(ctx: Context<SettleFunds>) -> Result<()> {
+        let deposit_account = &ctx.accounts.deposit_account;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        let coin_wallet_before = ctx.accounts.coin_wallet.amount;
+        let pc_wallet_before = ctx.accounts.pc_wallet.amount;
+
+        dex::settle_funds(CpiContext::new_with_signer(
+            ctx.accounts.dex_program.to_account_info(),
+            dex::SettleFunds {
+                market: ctx.accounts.market.to_account_info(),
+                open_orders: ctx.accounts.open_orders.to_account_info(),
+                open_orders_authority: ctx.accounts.pda_auth.to_account_info(),
+                coin_vault: ctx.accounts.coin_vault.to_account_info(),
+                pc_vault: ctx.accounts.pc_vault.to_account_info(),
+                coin_wallet: ctx.accounts.coin_wallet.to_account_info(),
+                pc_wallet: ctx.accounts.pc_wallet.to_account_info(),
+                vault_signer: ctx.accounts.vault_signer.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            signer,
+        ))?;
+
+        // settled proceeds and unused order funds return to the owner's balances
+        ctx.accounts.coin_wallet.reload()?;
+        ctx.accounts.pc_wallet.reload()?;
+        let coin_settled = ctx.accounts.coin_wallet.amount.saturating_sub(coin_wallet_before);
+        let pc_settled = ctx.accounts.pc_wallet.amount.saturating_sub(pc_wallet_before);
+
+        let deposit_key = ctx.accounts.deposit_account.key();
+        let owner = ctx.accounts.deposit_auth.key();
+
+        let coin_balance = &mut ctx.accounts.coin_balance;
+        coin_balance.deposit_account = deposit_key;
+        coin_balance.owner = owner;
+        coin_balance.mint = Some(ctx.accounts.coin_wallet.mint);
+        let coin_before = coin_balance.amount;
+        coin_balance.credit(coin_settled)?;
+        let coin_after = coin_balance.amount;
+
+        let pc_balance = &mut ctx.accounts.pc_balance;
+        pc_balance.deposit_account = deposit_key;
+        pc_balance.owner = owner;
+        pc_balance.mint = Some(ctx.accounts.pc_wallet.mint);
+        let pc_before = pc_balance.amount;
+        pc_balance.credit(pc_settled)?;
+        let pc_after = pc_balance.amount;
+
+        let deposit_account = &mut ctx.accounts.deposit_account;
+        deposit_account.track_balance(coin_before, coin_after);
+        deposit_account.track_balance(pc_before, pc_after);
+
+        Ok(())
+    }
+
+    // closes an empty open orders account and returns its rent to the depositor
+    pub fn close_open_orders(ctx: Context<CloseOpenOrders>) -> Result<()> {
+        let deposit_account = &ctx.accounts.deposit_account;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        dex::close_open_orders(CpiContext::new_with_signer(
+            ctx.accounts.dex_program.to_account_info(),
+            dex::CloseOpenOrders {
+                open_orders: ctx.accounts.open_orders.to_account_info(),
+                authority: ctx.accounts.pda_auth.to_account_info(),
+                destination: ctx.accounts.deposit_auth.to_account_info(),
+                market: ctx.accounts.market.to_account_info(),
+            },
+            signer,
+        ))?;
+
+        ctx.accounts.deposit_account.open_positions.open_orders -= 1;
+
+        Ok(())
+    }
+
+    // permissionless crank: processes up to `limit` fill events for the open orders accounts passed as
+    // remaining accounts, so filled orders can be settled. anchor_spl::dex has no wrapper for it.
+    pub fn consume_events<'info>(ctx: Context<'_, '_, '_, 'info, ConsumeEvents<'info>>, limit: u16) -> Result<()> {
+        let open_orders: Vec<&Pubkey> = ctx.remaining_accounts.iter().map(|a| a.key).collect();
+
+        let ix = serum_dex::instruction::consume_events(
+            ctx.accounts.dex_program.key,
+            open_orders,
+            ctx.accounts.market.key,
+            ctx.accounts.event_queue.key,
+            ctx.accounts.coin_fee_receivable_account.key,
+            ctx.accounts.pc_fee_receivable_account.key,
+            limit,
+        )
+        .map_err(ProgramError::from)?;
+
+        let mut account_infos = ctx.remaining_accounts.to_vec();
+        account_infos.push(ctx.accounts.market.to_account_info());
+        account_infos.push(ctx.accounts.event_queue.to_account_info());
+        account_infos.push(ctx.accounts.coin_fee_receivable_account.to_account_info());
+        account_infos.push(ctx.accounts.pc_fee_receivable_account.to_account_info());
+        account_infos.push(ctx.accounts.dex_program.to_account_info());
+
+        solana_program::program::invoke(&ix, &account_infos)?;
+
+        Ok(())
+    }
+
+    // offers deposited tokens (or an NFT) at a price in another SPL token. The offered amount is moved
+    // from the vault into an escrow owned by pda_auth until the limit is accepted or removed.
+    pub fn create_limit(ctx: Context<CreateLimit>, asset_type: AssetType, amount: u64, ask_price_per_asset: u64) -> Result<()> {
+        // a limit at any price could be taken by a wallet of the owner's choosing
+        ctx.accounts.deposit_account.check_direct_withdrawal(ctx.accounts.deposit_auth.key)?;
+
+        if amount == 0 {
+            return Err(Errors::InvalidLimitAmount.into());
+        }
+        if ask_price_per_asset == 0 {
+            return Err(Errors::InvalidAskPrice.into());
+        }
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let ask_token_program = ctx.accounts.ask_token_program.to_account_info();
+        let asset_mint = ctx.accounts.asset_mint.to_account_info();
+        let limit_escrow = ctx.accounts.limit_escrow.to_account_info();
+
+        token_interface::check_token_program(&token_program)?;
+        token_interface::check_token_program(&ask_token_program)?;
+        let decimals = token_interface::mint_decimals(&asset_mint, &token_program)?;
+        token_interface::mint_decimals(&ctx.accounts.ask_mint, &ask_token_program)?;
+
+        // an NFT is a single indivisible token
+        let is_nft = token_interface::is_nft(&asset_mint, &token_program)?;
+        if asset_type == AssetType::Nft && (amount != 1 || !is_nft) {
+            return Err(Errors::InvalidNft.into());
+        }
+        if asset_type == AssetType::Spl && is_nft {
+            return Err(Errors::NftNotFungible.into());
+        }
+
+        let deposit_account = &ctx.accounts.deposit_account;
+        let limit_key = ctx.accounts.limit.key();
+        let escrow_bump = *ctx.bumps.get("limit_escrow").ok_or(Errors::MissingBump)?;
+
+        let escrow_seeds = &[b"limit_escrow", limit_key.as_ref(), &[escrow_bump]];
+
+        token_interface::create_token_account(
+            &token_program,
+            &ctx.accounts.deposit_auth,
+            &limit_escrow,
+            &asset_mint,
+            ctx.accounts.pda_auth.key,
+            &ctx.accounts.system_program,
+            &[&escrow_seeds[..]],
+        )?;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        token_interface::transfer_checked(
+            &token_program,
+            &ctx.accounts.vault_asset_acct,
+            &asset_mint,
+            &limit_escrow,
+            &ctx.accounts.pda_auth,
+            amount,
+            decimals,
+            signer,
+        )?;
+
+        // a transfer fee withholds part of the amount, only what arrived in escrow is offered
+        let escrowed = token_interface::token_amount(&limit_escrow, asset_mint.key, None, &token_program)?;
+        if escrowed == 0 {
+            return Err(Errors::InvalidLimitAmount.into());
+        }
+
+        let limit = &mut ctx.accounts.limit;
+
+        limit.deposit_account = deposit_account.key();
+        limit.asset_holding_pda = Some(limit_escrow.key());
+        limit.asset = Asset {
+            asset_type,
+            asset_metadata: None,
+            asset_mint: Some(asset_mint.key()),
+        };
+        limit.asset_amount = escrowed;
+        limit.ask_price_per_asset = ask_price_per_asset;
+        limit.ask_asset = Asset {
+            asset_type: AssetType::Spl,
+            asset_metadata: None,
+            asset_mint: Some(ctx.accounts.ask_mint.key()),
+        };
+        // payments are made into the vault of the limit's owner
+        limit.ask_asset_pda = Some(get_associated_token_address_with_program_id(
+            ctx.accounts.pda_auth.key,
+            ctx.accounts.ask_mint.key,
+            ask_token_program.key,
+        ));
+        limit.bump = *ctx.bumps.get("limit").ok_or(Errors::MissingBump)?;
+        limit.escrow_bump = escrow_bump;
+
+        // escrowed assets are no longer withdrawable by the owner, and only unlocked ones can be offered
+        let now = Clock::get()?.unix_timestamp;
+        let lock = ctx.accounts.deposit_account.lock.clone();
+        if asset_type == AssetType::Nft && lock.as_ref().map_or(false, |lock| now < lock.end_ts) {
+            return Err(Errors::FundsLocked.into());
+        }
+        match asset_type {
+            AssetType::Spl => {
+                let owner_balance = ctx.accounts.owner_balance.as_mut().ok_or(Errors::MissingAssetAccount)?;
+                let before = owner_balance.amount;
+                owner_balance.debit_unlocked(amount, &lock, now)?;
+                let after = owner_balance.amount;
+                ctx.accounts.deposit_account.track_balance(before, after);
+            }
+            AssetType::Nft => {
+                let inventory = ctx.accounts.nft_inventory.as_mut().ok_or(Errors::MissingAssetAccount)?;
+                inventory.remove(asset_mint.key)?;
+                ctx.accounts.deposit_account.open_positions.nfts = inventory.nfts.len() as u32;
+            }
+        }
+
+        ctx.accounts.deposit_account.open_positions.limits += 1;
+
+        Ok(())
+    }
+
+    pub fn update_limit(ctx: Context<UpdateLimit>, ask_price_per_asset: u64) -> Result<()> {
+        if ask_price_per_asset == 0 {
+            return Err(Errors::InvalidAskPrice.into());
+        }
+
+        ctx.accounts.limit.ask_price_per_asset = ask_price_per_asset;
+
+        Ok(())
+    }
+
+    // returns the escrowed asset to the vault and closes the limit
+    pub fn remove_limit(ctx: Context<RemoveLimit>) -> Result<()> {
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let asset_mint = ctx.accounts.asset_mint.to_account_info();
+        let limit_escrow = ctx.accounts.limit_escrow.to_account_info();
+        let vault_asset_acct = ctx.accounts.vault_asset_acct.to_account_info();
+        let pda_auth = ctx.accounts.pda_auth.to_account_info();
+
+        token_interface::check_token_program(&token_program)?;
+        let decimals = token_interface::mint_decimals(&asset_mint, &token_program)?;
+        let escrowed = token_interface::token_amount(&limit_escrow, asset_mint.key, Some(pda_auth.key), &token_program)?;
+        let vault_before = token_interface::token_amount(&vault_asset_acct, asset_mint.key, Some(pda_auth.key), &token_program)?;
+
+        let deposit_account = &ctx.accounts.deposit_account;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        token_interface::transfer_checked(
+            &token_program,
+            &limit_escrow,
+            &asset_mint,
+            &vault_asset_acct,
+            &pda_auth,
+            escrowed,
+            decimals,
+            signer,
+        )?;
+
+        // with a transfer fee only what arrived back in the vault is credited
+        let vault_after = token_interface::token_amount(&vault_asset_acct, asset_mint.key, None, &token_program)?;
+        let returned = vault_after.checked_sub(vault_before).ok_or(Errors::Overflow)?;
+
+        token_interface::harvest_withheld(&token_program, &asset_mint, &limit_escrow)?;
+        token_interface::close_account(
+            &token_program,
+            &limit_escrow,
+            &ctx.accounts.deposit_auth,
+            &pda_auth,
+            signer,
+        )?;
+
+        match ctx.accounts.limit.asset.asset_type {
+            AssetType::Spl => {
+                let owner_balance = ctx.accounts.owner_balance.as_mut().ok_or(Errors::MissingAssetAccount)?;
+                let before = owner_balance.amount;
+                owner_balance.credit(returned)?;
+                let after = owner_balance.amount;
+                ctx.accounts.deposit_account.track_balance(before, after);
+            }
+            AssetType::Nft => {
+                let inventory = ctx.accounts.nft_inventory.as_mut().ok_or(Errors::MissingAssetAccount)?;
+                inventory.add(asset_mint.key())?;
+                ctx.accounts.deposit_account.open_positions.nfts = inventory.nfts.len() as u32;
+            }
+        }
+
+        ctx.accounts.deposit_account.open_positions.limits -= 1;
+
+        Ok(())
+    }
+
+    // buys `quantity` of the offered asset: the taker pays the ask price into the owner's vault and
+    // receives the asset from escrow in the same transaction. The limit closes once it is sold out.
+    pub fn accept_limit(ctx: Context<AcceptLimit>, quantity: u64) -> Result<()> {
+        ctx.accounts.deposit_account.check_direct_withdrawal(ctx.accounts.taker.key)?;
+
+        let limit = &mut ctx.accounts.limit;
+
+        if quantity == 0 || quantity > limit.asset_amount {
+            return Err(Errors::InvalidLimitAmount.into());
+        }
+        let price = quantity
+            .checked_mul(limit.ask_price_per_asset)
+            .ok_or(Errors::Overflow)?;
+
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let ask_token_program = ctx.accounts.ask_token_program.to_account_info();
+        let asset_mint = ctx.accounts.asset_mint.to_account_info();
+        let ask_mint = ctx.accounts.ask_mint.to_account_info();
+        let limit_escrow = ctx.accounts.limit_escrow.to_account_info();
+        let vault_payment_acct = ctx.accounts.vault_payment_acct.to_account_info();
+        let pda_auth = ctx.accounts.pda_auth.to_account_info();
+
+        token_interface::check_token_program(&token_program)?;
+        token_interface::check_token_program(&ask_token_program)?;
+        let asset_decimals = token_interface::mint_decimals(&asset_mint, &token_program)?;
+        let ask_decimals = token_interface::mint_decimals(&ask_mint, &ask_token_program)?;
+
+        if vault_payment_acct.data_is_empty() {
+            let cpi_accounts = Create {
+                payer: ctx.accounts.taker.to_account_info(),
+                associated_token: vault_payment_acct.clone(),
+                authority: pda_auth.clone(),
+                mint: ask_mint.clone(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: ask_token_program.clone(),
+            };
+
+            let cpi = CpiContext::new(ctx.accounts.associated_token_program.to_account_info(), cpi_accounts);
+
+            create(cpi)?;
+        }
+
+        token_interface::token_amount(
+            &ctx.accounts.taker_payment_acct,
+            ask_mint.key,
+            Some(ctx.accounts.taker.key),
+            &ask_token_program,
+        )?;
+        token_interface::token_amount(&ctx.accounts.taker_asset_acct, asset_mint.key, None, &token_program)?;
+        let vault_before = token_interface::token_amount(&vault_payment_acct, ask_mint.key, Some(pda_auth.key), &ask_token_program)?;
+
+        // step 1: taker pays the owner's vault
+        token_interface::transfer_checked(
+            &ask_token_program,
+            &ctx.accounts.taker_payment_acct,
+            &ask_mint,
+            &vault_payment_acct,
+            &ctx.accounts.taker,
+            price,
+            ask_decimals,
+            &[],
+        )?;
+
+        // a transfer fee on the ask asset is paid out of the price, only what arrived is credited
+        let vault_after = token_interface::token_amount(&vault_payment_acct, ask_mint.key, None, &ask_token_program)?;
+        let received = vault_after.checked_sub(vault_before).ok_or(Errors::Overflow)?;
+
+        let owner_balance = &mut ctx.accounts.owner_balance;
+        owner_balance.deposit_account = ctx.accounts.deposit_account.key();
+        owner_balance.owner = ctx.accounts.deposit_auth.key();
+        owner_balance.mint = Some(ask_mint.key());
+        let before = owner_balance.amount;
+        owner_balance.credit(received)?;
+        let after = owner_balance.amount;
+        ctx.accounts.deposit_account.track_balance(before, after);
+
+        // step 2: escrow releases the asset to the taker
+        let deposit_account = &ctx.accounts.deposit_account;
+
+        let seeds = &[
+            b"auth",
+            deposit_account.to_account_info().key.as_ref(),
+            &[deposit_account.auth_bump],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        token_interface::transfer_checked(
+            &token_program,
+            &limit_escrow,
+            &asset_mint,
+            &ctx.accounts.taker_asset_acct,
+            &pda_auth,
+            quantity,
+            asset_decimals,
+            signer,
+        )?;
+
+        limit.asset_amount -= quantity;
+
+        if limit.asset_amount == 0 {
+            token_interface::harvest_withheld(&token_program, &asset_mint, &limit_escrow)?;
+            token_interface::close_account(
+                &token_program,
+                &limit_escrow,
+                &ctx.accounts.deposit_auth,
+                &pda_auth,
+                signer,
+            )?;
+
+            limit.close(ctx.accounts.deposit_auth.to_account_info())?;
+            ctx.accounts.deposit_account.open_positions.limits -= 1;
+        }
+
+        Ok(())
+    }
+
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(init, payer = deposit_auth, space = DepositBase::LEN)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(seeds = [b"sol_vault", pda_auth.key().as_ref()], bump)]
+    pub sol_vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub deposit_auth: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositNative<'info> {
+    #[account(mut)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"sol_vault", pda_auth.key().as_ref()], bump = deposit_account.sol_vault_bump)]
+    pub sol_vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    /// CHECK: owner of the credited balance, the depositor for a plain deposit.
+    pub beneficiary: UncheckedAccount<'info>,
+    #[account(init_if_needed, payer = depositor, space = UserBalance::LEN,
+        seeds = [b"balance", deposit_account.key().as_ref(), beneficiary.key().as_ref()], bump
+    )]
+    pub balance: Account<'info, UserBalance>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawNative<'info> {
+    #[account(mut)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"sol_vault", pda_auth.key().as_ref()], bump = deposit_account.sol_vault_bump)]
+    pub sol_vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    #[account(mut, seeds = [b"balance", deposit_account.key().as_ref(), depositor.key().as_ref()], bump)]
+    pub balance: Account<'info, UserBalance>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositSpl<'info> {
+    #[account(mut)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    #[account(mut,
+        address = get_associated_token_address_with_program_id(pda_auth.key, token_mint.key, token_program.key)
+            @ Errors::InvalidTokenAccount
+    )]
+    /// CHECK: vault associated token account, created by the handler if needed.
+    pub to_token_acct: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub from_token_acct: UncheckedAccount<'info>,
+    /// CHECK: owner of the credited balance, the depositor for a plain deposit.
+    pub beneficiary: UncheckedAccount<'info>,
+    #[account(init_if_needed, payer = depositor, space = UserBalance::LEN,
+        seeds = [b"balance", deposit_account.key().as_ref(), beneficiary.key().as_ref(), token_mint.key().as_ref()], bump
+    )]
+    pub balance: Account<'info, UserBalance>,
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub token_mint: UncheckedAccount<'info>,
+    /// CHECK: checked by the handler to be the legacy token program or Token-2022.
+    pub token_program: UncheckedAccount<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawSpl<'info> {
+    #[account(mut)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub to_token_acct: UncheckedAccount<'info>,
+    #[account(mut,
+        address = get_associated_token_address_with_program_id(pda_auth.key, token_mint.key, token_program.key)
+            @ Errors::InvalidTokenAccount
+    )]
+    /// CHECK: vault associated token account.
+    pub from_token_acct: UncheckedAccount<'info>,
+    #[account(mut,
+        seeds = [b"balance", deposit_account.key().as_ref(), depositor.key().as_ref(), token_mint.key().as_ref()], bump
+    )]
+    pub balance: Account<'info, UserBalance>,
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub token_mint: UncheckedAccount<'info>,
+    /// CHECK: checked by the handler to be the legacy token program or Token-2022.
+    pub token_program: UncheckedAccount<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseDeposit<'info> {
+    #[account(mut, has_one = deposit_auth, close = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"sol_vault", pda_auth.key().as_ref()], bump = deposit_account.sol_vault_bump)]
+    pub sol_vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub deposit_auth: Signer<'info>,
+    // only needed when NFTs have been deposited
+    #[account(mut, seeds = [b"nft_inventory", deposit_account.key().as_ref()], bump, close = deposit_auth)]
+    pub nft_inventory: Option<Account<'info, NftInventory>>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    // only needed to close Token-2022 accounts or refund Token-2022 balances
+    #[account(address = spl_token_2022::ID)]
+    /// CHECK: the Token-2022 program.
+    pub token_2022_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct SetLock<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    pub deposit_auth: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetNftCollection<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    pub deposit_auth: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMultisig<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    pub deposit_auth: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeTransfer<'info> {
+    #[account(mut)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(init, payer = proposer, space = PendingTransfer::LEN,
+        seeds = [
+            b"pending_transfer",
+            deposit_account.key().as_ref(),
+            &deposit_account.multisig.as_ref().map_or(0, |multisig| multisig.transfer_count).to_le_bytes(),
+        ],
+        bump
+    )]
+    pub pending_transfer: Account<'info, PendingTransfer>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveTransfer<'info> {
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(mut, has_one = deposit_account)]
+    pub pending_transfer: Account<'info, PendingTransfer>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelTransfer<'info> {
+    #[account(mut)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(mut, has_one = deposit_account, has_one = proposer, close = proposer)]
+    pub pending_transfer: Account<'info, PendingTransfer>,
+    #[account(mut)]
+    /// CHECK: receives the rent of the pending transfer.
+    pub proposer: UncheckedAccount<'info>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteNativeTransfer<'info> {
+    #[account(mut)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"sol_vault", pda_auth.key().as_ref()], bump = deposit_account.sol_vault_bump)]
+    pub sol_vault: SystemAccount<'info>,
+    #[account(mut, has_one = deposit_account, has_one = proposer, has_one = to, close = proposer)]
+    pub pending_transfer: Account<'info, PendingTransfer>,
+    #[account(mut,
+        seeds = [b"balance", deposit_account.key().as_ref(), deposit_account.deposit_auth.as_ref()], bump
+    )]
+    pub balance: Account<'info, UserBalance>,
+    #[account(mut)]
+    pub to: SystemAccount<'info>,
+    #[account(mut)]
+    /// CHECK: receives the rent of the pending transfer.
+    pub proposer: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteSplTransfer<'info> {
+    #[account(mut)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut, has_one = deposit_account, has_one = proposer, close = proposer)]
+    pub pending_transfer: Account<'info, PendingTransfer>,
+    #[account(mut,
+        seeds = [b"balance", deposit_account.key().as_ref(), deposit_account.deposit_auth.as_ref(), token_mint.key().as_ref()], bump
+    )]
+    pub balance: Account<'info, UserBalance>,
+    #[account(mut,
+        address = get_associated_token_address_with_program_id(pda_auth.key, token_mint.key, token_program.key)
+            @ Errors::InvalidTokenAccount
+    )]
+    /// CHECK: vault associated token account.
+    pub from_token_acct: UncheckedAccount<'info>,
+    #[account(mut, address = pending_transfer.to)]
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub to_token_acct: UncheckedAccount<'info>,
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub token_mint: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: receives the rent of the pending transfer.
+    pub proposer: UncheckedAccount<'info>,
+    /// CHECK: checked by the handler to be the legacy token program or Token-2022.
+    pub token_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteNftTransfer<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut, has_one = deposit_account, has_one = proposer, close = proposer)]
+    pub pending_transfer: Account<'info, PendingTransfer>,
+    #[account(mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = pda_auth,
+    )]
+    pub from_token_acct: Account<'info, TokenAccount>,
+    #[account(mut, address = pending_transfer.to, token::mint = nft_mint)]
+    pub to_token_acct: Account<'info, TokenAccount>,
+    pub nft_mint: Account<'info, Mint>,
+    #[account(mut, seeds = [b"nft_inventory", deposit_account.key().as_ref()], bump)]
+    pub nft_inventory: Account<'info, NftInventory>,
+    #[account(mut)]
+    /// CHECK: receives the rent of the pending transfer.
+    pub proposer: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: receives the rent of the vault's NFT token account, which it paid for on deposit.
+    pub deposit_auth: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Withdrawable<'info> {
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(constraint = balance.deposit_account == deposit_account.key())]
+    pub balance: Account<'info, UserBalance>,
+}
+
+#[derive(Accounts)]
+pub struct DepositShares<'info> {
+    #[account(mut)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    // pooled tokens are kept apart from the recorded balances in the vault's associated token account
+    #[account(init_if_needed, payer = depositor,
+        token::mint = token_mint,
+        token::authority = pda_auth,
+        seeds = [b"share_vault", deposit_account.key().as_ref(), token_mint.key().as_ref()], bump
+    )]
+    pub share_vault: Account<'info, TokenAccount>,
+    #[account(init_if_needed, payer = depositor,
+        mint::decimals = token_mint.decimals,
+        mint::authority = pda_auth,
+        seeds = [b"share_mint", deposit_account.key().as_ref(), token_mint.key().as_ref()], bump
+    )]
+    pub share_mint: Account<'info, Mint>,
+    #[account(init_if_needed, payer = depositor, space = SharePool::LEN,
+        seeds = [b"share_pool", deposit_account.key().as_ref(), token_mint.key().as_ref()], bump
+    )]
+    pub share_pool: Account<'info, SharePool>,
+    #[account(init_if_needed, payer = depositor,
+        associated_token::mint = share_mint,
+        associated_token::authority = depositor,
+    )]
+    pub share_token_acct: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = token_mint)]
+    pub from_token_acct: Account<'info, TokenAccount>,
+    pub token_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct RedeemShares<'info> {
+    #[account(mut)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    pub depositor: Signer<'info>,
+    #[account(mut, seeds = [b"share_vault", deposit_account.key().as_ref(), token_mint.key().as_ref()], bump)]
+    pub share_vault: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"share_mint", deposit_account.key().as_ref(), token_mint.key().as_ref()], bump)]
+    pub share_mint: Account<'info, Mint>,
+    #[account(mut, seeds = [b"share_pool", deposit_account.key().as_ref(), token_mint.key().as_ref()], bump)]
+    pub share_pool: Account<'info, SharePool>,
+    #[account(mut, token::mint = share_mint, token::authority = depositor)]
+    pub share_token_acct: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = token_mint)]
+    pub to_token_acct: Account<'info, TokenAccount>,
+    pub token_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct DepositNft<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub deposit_auth: Signer<'info>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = nft_mint,
+        payer = deposit_auth,
+        associated_token::authority = pda_auth,
+    )]
+    pub to_token_acct: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = nft_mint, token::authority = deposit_auth)]
+    pub from_token_acct: Account<'info, TokenAccount>,
+    #[account(constraint = nft_mint.decimals == 0 && nft_mint.supply == 1 @ Errors::InvalidNftMint)]
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        seeds = [b"metadata", metadata_program.key().as_ref(), nft_mint.key().as_ref()], bump,
+        seeds::program = metadata_program.key()
+    )]
+    pub metadata: Account<'info, MetadataAccount>,
+    #[account(init_if_needed, payer = deposit_auth, space = NftInventory::LEN,
+        seeds = [b"nft_inventory", deposit_account.key().as_ref()], bump
+    )]
+    pub nft_inventory: Account<'info, NftInventory>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub metadata_program: Program<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawNft<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub deposit_auth: Signer<'info>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = nft_mint,
+        payer = deposit_auth,
+        associated_token::authority = deposit_auth,
+    )]
+    pub to_token_acct: Account<'info, TokenAccount>,
+    #[account(mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = pda_auth,
+    )]
+    pub from_token_acct: Account<'info, TokenAccount>,
+    pub nft_mint: Account<'info, Mint>,
+    #[account(mut, seeds = [b"nft_inventory", deposit_account.key().as_ref()], bump)]
+    pub nft_inventory: Account<'info, NftInventory>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeMarket<'info> {
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub market: AccountInfo<'info>,
+    pub coin_mint: Account<'info, Mint>,
+    pub pc_mint: Account<'info, Mint>,
+    #[account(token::mint = coin_mint)]
+    pub coin_vault: Account<'info, TokenAccount>,
+    #[account(token::mint = pc_mint)]
+    pub pc_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub bids: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub asks: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub req_q: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub event_q: AccountInfo<'info>,
+    // one record per market, so markets can be looked up by address without a shared account to fill up
+    #[account(init, payer = payer, space = MarketInfo::LEN, seeds = [b"market_info", market.key().as_ref()], bump)]
+    pub market_info: Account<'info, MarketInfo>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub dex_program: Program<'info, Dex>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct NewOrder<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this. Owner of the open orders account.
+    pub pda_auth: UncheckedAccount<'info>,
+    pub deposit_auth: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub market: AccountInfo<'info>,
+    #[account(mut, seeds = [b"open_orders", deposit_account.key().as_ref(), market.key().as_ref()], bump)]
+    /// CHECK: checked by the dex program.
+    pub open_orders: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub request_queue: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub event_queue: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub market_bids: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub market_asks: AccountInfo<'info>,
+    // Vault token account where funds are transferred from for the order. If
+    // posting a bid market A/B, then this is the SPL token account for B.
+    #[account(mut,
+        address = get_associated_token_address_with_program_id(pda_auth.key, &order_payer_token_account.mint, token_program.key)
+            @ Errors::InvalidTokenAccount
+    )]
+    pub order_payer_token_account: Account<'info, TokenAccount>,
+    // orders are paid out of the owner's balance of the payer mint
+    #[account(mut,
+        seeds = [b"balance", deposit_account.key().as_ref(), deposit_auth.key().as_ref(), order_payer_token_account.mint.as_ref()], bump
+    )]
+    pub owner_balance: Account<'info, UserBalance>,
+    // Also known as the "base" currency. For a given A/B market,
+    // this is the vault for the A mint.
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub coin_vault: AccountInfo<'info>,
+    // Also known as the "quote" currency. For a given A/B market,
+    // this is the vault for the B mint.
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub pc_vault: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub dex_program: Program<'info,Dex>,
+    pub rent: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this. Owner of the open orders account.
+    pub pda_auth: UncheckedAccount<'info>,
+    pub deposit_auth: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub market: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub market_bids: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub market_asks: AccountInfo<'info>,
+    #[account(mut, seeds = [b"open_orders", deposit_account.key().as_ref(), market.key().as_ref()], bump)]
+    /// CHECK: checked by the dex program.
+    pub open_orders: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub event_queue: AccountInfo<'info>,
+    pub dex_program: Program<'info, Dex>,
+}
+
+#[derive(Accounts)]
+pub struct InitOpenOrders<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this. Owner of the open orders account.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub deposit_auth: Signer<'info>,
+    #[account(init, payer = deposit_auth, space = OPEN_ORDERS_LEN, owner = dex_program.key(),
+        seeds = [b"open_orders", deposit_account.key().as_ref(), market.key().as_ref()], bump
+    )]
+    /// CHECK: initialized by the dex program.
+    pub open_orders: UncheckedAccount<'info>,
+    /// CHECK: checked by the dex program.
+    pub market: AccountInfo<'info>,
+    pub dex_program: Program<'info, Dex>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SettleFunds<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this. Owner of the open orders account.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub deposit_auth: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub market: AccountInfo<'info>,
+    #[account(mut, seeds = [b"open_orders", deposit_account.key().as_ref(), market.key().as_ref()], bump)]
+    /// CHECK: checked by the dex program.
+    pub open_orders: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub coin_vault: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub pc_vault: AccountInfo<'info>,
+    // vault token accounts the settled funds are paid into
+    #[account(mut,
+        address = get_associated_token_address_with_program_id(pda_auth.key, &coin_wallet.mint, token_program.key)
+            @ Errors::InvalidTokenAccount
+    )]
+    pub coin_wallet: Account<'info, TokenAccount>,
+    #[account(mut,
+        address = get_associated_token_address_with_program_id(pda_auth.key, &pc_wallet.mint, token_program.key)
+            @ Errors::InvalidTokenAccount
+    )]
+    pub pc_wallet: Account<'info, TokenAccount>,
+    // the owner's balances the settled funds are credited to
+    #[account(init_if_needed, payer = deposit_auth, space = UserBalance::LEN,
+        seeds = [b"balance", deposit_account.key().as_ref(), deposit_auth.key().as_ref(), coin_wallet.mint.as_ref()], bump
+    )]
+    pub coin_balance: Account<'info, UserBalance>,
+    #[account(init_if_needed, payer = deposit_auth, space = UserBalance::LEN,
+        seeds = [b"balance", deposit_account.key().as_ref(), deposit_auth.key().as_ref(), pc_wallet.mint.as_ref()], bump
+    )]
+    pub pc_balance: Account<'info, UserBalance>,
+    /// CHECK: checked by the dex program.
+    pub vault_signer: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub dex_program: Program<'info, Dex>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseOpenOrders<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this. Owner of the open orders account.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub deposit_auth: Signer<'info>,
+    #[account(mut, seeds = [b"open_orders", deposit_account.key().as_ref(), market.key().as_ref()], bump)]
+    /// CHECK: checked by the dex program.
+    pub open_orders: AccountInfo<'info>,
+    /// CHECK: checked by the dex program.
+    pub market: AccountInfo<'info>,
+    pub dex_program: Program<'info, Dex>,
+}
+
+// the open orders accounts to crank are passed as writable remaining accounts
+#[derive(Accounts)]
+pub struct ConsumeEvents<'info> {
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub market: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the dex program.
+    pub event_queue: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: unused by the dex program, still required by the instruction.
+    pub coin_fee_receivable_account: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: unused by the dex program, still required by the instruction.
+    pub pc_fee_receivable_account: AccountInfo<'info>,
+    pub dex_program: Program<'info, Dex>,
+}
+
+#[derive(Accounts)]
+pub struct CreateLimit<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub deposit_auth: Signer<'info>,
+    #[account(init, payer = deposit_auth, space = Limit::LEN,
+        seeds = [b"limit", deposit_account.key().as_ref(), asset_mint.key().as_ref()], bump
+    )]
+    pub limit: Account<'info, Limit>,
+    #[account(mut, seeds = [b"limit_escrow", limit.key().as_ref()], bump)]
+    /// CHECK: token account of the asset mint's program, created by the handler.
+    pub limit_escrow: UncheckedAccount<'info>,
+    #[account(mut,
+        address = get_associated_token_address_with_program_id(pda_auth.key, asset_mint.key, token_program.key)
+            @ Errors::InvalidTokenAccount
+    )]
+    /// CHECK: vault associated token account.
+    pub vault_asset_acct: UncheckedAccount<'info>,
+    // the owner's balance of a fungible asset, or the inventory holding an NFT
+    #[account(mut,
+        seeds = [b"balance", deposit_account.key().as_ref(), deposit_auth.key().as_ref(), asset_mint.key().as_ref()], bump
+    )]
+    pub owner_balance: Option<Account<'info, UserBalance>>,
+    #[account(mut, seeds = [b"nft_inventory", deposit_account.key().as_ref()], bump)]
+    pub nft_inventory: Option<Account<'info, NftInventory>>,
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub asset_mint: UncheckedAccount<'info>,
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub ask_mint: UncheckedAccount<'info>,
+    /// CHECK: program of the asset mint, checked by the handler.
+    pub token_program: UncheckedAccount<'info>,
+    /// CHECK: program of the ask mint, checked by the handler.
+    pub ask_token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
 
 #[derive(Accounts)]
-pub struct MintftAndCreateMetadata<'info> {
+pub struct UpdateLimit<'info> {
     #[account(has_one = deposit_auth)]
     pub deposit_account: Account<'info, DepositBase>,
+    #[account(mut, has_one = deposit_account)]
+    pub limit: Account<'info, Limit>,
+    pub deposit_auth: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveLimit<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
     #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
     /// CHECK: no need to check this.
     pub pda_auth: UncheckedAccount<'info>,
-    #[account(init, payer = deposit_auth, space = MetadataAccount::LEN)]
-    pub metadata: Account<'info, MetadataAccount>,
     #[account(mut)]
-    pub mint: Account<'info, Mint>,
+    pub deposit_auth: Signer<'info>,
+    #[account(mut, has_one = deposit_account, close = deposit_auth)]
+    pub limit: Account<'info, Limit>,
+    #[account(mut, seeds = [b"limit_escrow", limit.key().as_ref()], bump = limit.escrow_bump)]
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub limit_escrow: UncheckedAccount<'info>,
+    #[account(mut,
+        address = get_associated_token_address_with_program_id(pda_auth.key, asset_mint.key, token_program.key)
+            @ Errors::InvalidTokenAccount
+    )]
+    /// CHECK: vault associated token account.
+    pub vault_asset_acct: UncheckedAccount<'info>,
+    // the owner's balance of a fungible asset, or the inventory holding an NFT
+    #[account(mut,
+        seeds = [b"balance", deposit_account.key().as_ref(), deposit_auth.key().as_ref(), asset_mint.key().as_ref()], bump
+    )]
+    pub owner_balance: Option<Account<'info, UserBalance>>,
+    #[account(mut, seeds = [b"nft_inventory", deposit_account.key().as_ref()], bump)]
+    pub nft_inventory: Option<Account<'info, NftInventory>>,
+    #[account(constraint = Some(asset_mint.key()) == limit.asset.asset_mint @ Errors::InvalidLimitAsset)]
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub asset_mint: UncheckedAccount<'info>,
+    /// CHECK: program of the asset mint, checked by the handler.
+    pub token_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptLimit<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
     #[account(mut)]
-    /// CHECK: add constraints later
-    pub edition: UncheckedAccount<'info>,
+    /// CHECK: owner of the limit, receives the rent once it is sold out.
+    pub deposit_auth: UncheckedAccount<'info>,
+    #[account(mut, has_one = deposit_account)]
+    pub limit: Account<'info, Limit>,
+    #[account(mut, seeds = [b"limit_escrow", limit.key().as_ref()], bump = limit.escrow_bump)]
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub limit_escrow: UncheckedAccount<'info>,
+    #[account(mut, constraint = Some(vault_payment_acct.key()) == limit.ask_asset_pda @ Errors::InvalidAskAsset)]
+    /// CHECK: vault associated token account of the ask mint, created by the handler if needed.
+    pub vault_payment_acct: UncheckedAccount<'info>,
+    // payments are credited to the owner's balance of the ask asset
+    #[account(init_if_needed, payer = taker, space = UserBalance::LEN,
+        seeds = [b"balance", deposit_account.key().as_ref(), deposit_auth.key().as_ref(), ask_mint.key().as_ref()], bump
+    )]
+    pub owner_balance: Account<'info, UserBalance>,
+    #[account(mut)]
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub taker_payment_acct: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub taker_asset_acct: UncheckedAccount<'info>,
+    #[account(constraint = Some(asset_mint.key()) == limit.asset.asset_mint @ Errors::InvalidLimitAsset)]
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub asset_mint: UncheckedAccount<'info>,
+    #[account(constraint = Some(ask_mint.key()) == limit.ask_asset.asset_mint @ Errors::InvalidAskAsset)]
+    /// CHECK: checked by the handler, it may belong to the legacy token program or Token-2022.
+    pub ask_mint: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    /// CHECK: program of the asset mint, checked by the handler.
+    pub token_program: UncheckedAccount<'info>,
+    /// CHECK: program of the ask mint, checked by the handler.
+    pub ask_token_program: UncheckedAccount<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintNft<'info> {
+    #[account(mut, has_one = deposit_auth)]
+    pub deposit_account: Account<'info, DepositBase>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this. Mint and update authority of the NFT.
+    pub pda_auth: UncheckedAccount<'info>,
     #[account(mut)]
     pub deposit_auth: Signer<'info>,
+    #[account(init, payer = deposit_auth,
+        mint::decimals = 0,
+        mint::authority = pda_auth,
+        mint::freeze_authority = pda_auth,
+    )]
+    pub mint: Account<'info, Mint>,
+    #[account(init, payer = deposit_auth,
+        associated_token::mint = mint,
+        associated_token::authority = pda_auth,
+    )]
+    pub vault_nft_acct: Account<'info, TokenAccount>,
+    #[account(mut,
+        seeds = [b"metadata", metadata_program.key().as_ref(), mint.key().as_ref()], bump,
+        seeds::program = metadata_program.key()
+    )]
+    /// CHECK: created by the token metadata program.
+    pub metadata: UncheckedAccount<'info>,
+    #[account(mut,
+        seeds = [b"metadata", metadata_program.key().as_ref(), mint.key().as_ref(), b"edition"], bump,
+        seeds::program = metadata_program.key()
+    )]
+    /// CHECK: created by the token metadata program.
+    pub edition: UncheckedAccount<'info>,
+    #[account(init_if_needed, payer = deposit_auth, space = NftInventory::LEN,
+        seeds = [b"nft_inventory", deposit_account.key().as_ref()], bump
+    )]
+    pub nft_inventory: Account<'info, NftInventory>,
     pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub metadata_program: Program<'info, Metadata>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
@@ -448,90 +2455,386 @@ pub struct MintftAndCreateMetadata<'info> {
 pub struct DepositBase {
     pub deposit_auth: Pubkey,
     pub auth_bump: u8,
-    pub sol_vault_bump: Option<u8>,
+    pub sol_vault_bump: u8,
+    pub lock: Option<VestingLock>,
+    pub multisig: Option<Multisig>,
+    pub nft_collection: Option<Pubkey>, // required verified collection of deposited NFTs
+    pub open_positions: OpenPositions,
 }
 
 impl DepositBase {
-    const LEN: usize = 8 + 32 + 1 + 1 + 1;
+    const LEN: usize =
+        8 + 32 + 1 + 1 + 1 + VestingLock::LEN + 1 + Multisig::LEN + OPTION_PUBKEY_LEN + OpenPositions::LEN;
+
+    // keeps count of the balances still holding funds
+    pub fn track_balance(&mut self, before: u64, after: u64) {
+        OpenPositions::track(&mut self.open_positions.balances, before, after);
+    }
+
+    // keeps count of the share pools still holding assets
+    pub fn track_pool(&mut self, before: u64, after: u64) {
+        OpenPositions::track(&mut self.open_positions.share_pools, before, after);
+    }
+
+    // in multisig mode the owner's assets only leave the vault through approved transfers
+    pub fn check_direct_withdrawal(&self, depositor: &Pubkey) -> Result<()> {
+        if self.multisig.is_some() && *depositor == self.deposit_auth {
+            return Err(Errors::MultisigRequired.into());
+        }
+        Ok(())
+    }
+
+    // the vault lock covers the owner's balances, a grant to anyone else carries its own
+    pub fn lock_for(&self, balance: &UserBalance) -> Option<VestingLock> {
+        if balance.owner == self.deposit_auth {
+            self.lock.clone()
+        } else {
+            balance.lock.clone()
+        }
+    }
+
+    pub fn check_approved(&self, pending_transfer: &PendingTransfer) -> Result<()> {
+        let multisig = self.multisig.as_ref().ok_or(Errors::MultisigNotSet)?;
+        if pending_transfer.approvals.len() < multisig.threshold as usize {
+            return Err(Errors::NotEnoughApprovals.into());
+        }
+        Ok(())
+    }
+}
+
+// what the vault still holds for someone, all of which must be settled before it can be closed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct OpenPositions {
+    pub balances: u32, // user balances with a non-zero amount
+    pub nfts: u32,
+    pub limits: u32,
+    pub transfers: u32, // pending multisig transfers
+    pub share_pools: u32, // share pools with assets
+    pub open_orders: u32, // dex open orders accounts
+}
+
+impl OpenPositions {
+    const LEN: usize = 4 * 6;
+
+    fn track(count: &mut u32, before: u64, after: u64) {
+        match (before == 0, after == 0) {
+            (true, false) => *count += 1,
+            (false, true) => *count -= 1,
+            _ => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.balances == 0
+            && self.nfts == 0
+            && self.limits == 0
+            && self.transfers == 0
+            && self.share_pools == 0
+            && self.open_orders == 0
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Multisig {
+    pub owners: Vec<Pubkey>,
+    pub threshold: u8,
+    pub transfer_count: u64, // seeds the next pending transfer
+}
+
+impl Multisig {
+    const MAX_OWNERS: usize = 10;
+    const LEN: usize = 4 + 32 * Self::MAX_OWNERS + 1 + 8;
 }
 
+// a withdrawal of the owner's balance or one of the vault's NFTs waiting for multisig approvals
+#[account]
+pub struct PendingTransfer {
+    pub deposit_account: Pubkey,
+    pub index: u64,
+    pub proposer: Pubkey,
+    pub asset_type: AssetType,
+    pub mint: Option<Pubkey>, // None for SOL
+    pub amount: u64, // 1 for an NFT
+    pub to: Pubkey,
+    pub approvals: Vec<Pubkey>,
+}
+
+impl PendingTransfer {
+    const LEN: usize = 8 + 32 + 8 + 32 + 1 + OPTION_PUBKEY_LEN + 8 + 32 + 4 + 32 * Multisig::MAX_OWNERS;
+}
+
+// nothing is withdrawable before the cliff, then balances vest linearly from start until end. Setting
+// all three to the same timestamp unlocks everything at once.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VestingLock {
+    pub start_ts: i64,
+    pub cliff_ts: i64,
+    pub end_ts: i64,
+}
+
+impl VestingLock {
+    const LEN: usize = 8 * 3;
+
+    pub fn check(&self) -> Result<()> {
+        if self.cliff_ts < self.start_ts || self.end_ts < self.cliff_ts {
+            return Err(Errors::InvalidLock.into());
+        }
+        Ok(())
+    }
+
+    pub fn vested(&self, total: u64, now: i64) -> u64 {
+        if now < self.cliff_ts {
+            0
+        } else if now >= self.end_ts {
+            total
+        } else {
+            let elapsed = (now - self.start_ts) as u128;
+            let duration = (self.end_ts - self.start_ts) as u128;
+            (total as u128 * elapsed / duration) as u64
+        }
+    }
+}
+
+
+// an offer of escrowed assets at a price in another asset
 #[account]
 pub struct Limit {
-    pub asset_holding_pda: Option<Pubkey>,
+    pub deposit_account: Pubkey, // vault that owns the offered asset
+    pub asset_holding_pda: Option<Pubkey>, // escrow token account
     pub asset: Asset,
-    pub ask_price_per_asset: u64,
+    pub asset_amount: u64, // left to sell
+    pub ask_price_per_asset: u64, // in base units of the ask asset per base unit of the asset
     pub ask_asset: Asset,
-    pub ask_asset_pda: Option<Pubkey>,
+    pub ask_asset_pda: Option<Pubkey>, // vault token account receiving payments
+    pub bump: u8,
+    pub escrow_bump: u8,
 }
 
-#[account]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct Asset {
-    pub asset_type: String,
+    pub asset_type: AssetType,
     pub asset_metadata: Option<Pubkey>,
     pub asset_mint: Option<Pubkey>,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq)]
+pub enum AssetType {
+    Spl,
+    Nft,
+}
+
+// a depositor's recorded share of a vault, of native SOL when `mint` is None
+#[account]
+pub struct UserBalance {
+    pub deposit_account: Pubkey,
+    pub owner: Pubkey,
+    pub mint: Option<Pubkey>,
+    pub amount: u64,
+    pub withdrawn: u64, // vesting applies to amount + withdrawn
+    pub lock: Option<VestingLock>, // set by the funder of a grant
+}
+
+impl UserBalance {
+    const LEN: usize = 8 + 32 + 32 + OPTION_PUBKEY_LEN + 8 + 8 + 1 + VestingLock::LEN;
+
+    // a grant's lock is set by the deposit that opens the balance, so it never covers funds of the
+    // beneficiary's own. The owner's balances are covered by the vault lock instead.
+    pub fn set_lock(&mut self, lock: VestingLock, vault_owner: &Pubkey) -> Result<()> {
+        if self.owner == *vault_owner || self.lock.is_some() || self.amount > 0 || self.withdrawn > 0 {
+            return Err(Errors::InvalidGrant.into());
+        }
+        lock.check()?;
+        self.lock = Some(lock);
+        Ok(())
+    }
+
+    pub fn withdrawable(&self, lock: &Option<VestingLock>, now: i64) -> u64 {
+        match lock {
+            None => self.amount,
+            Some(lock) => {
+                let vested = lock.vested(self.amount.saturating_add(self.withdrawn), now);
+                vested.saturating_sub(self.withdrawn).min(self.amount)
+            }
+        }
+    }
+
+    pub fn withdraw(&mut self, amount: u64, lock: &Option<VestingLock>, now: i64) -> Result<()> {
+        if amount > self.amount {
+            return Err(Errors::InsufficientBalance.into());
+        }
+        if amount > self.withdrawable(lock, now) {
+            return Err(Errors::FundsLocked.into());
+        }
+        self.amount -= amount;
+        self.withdrawn = self.withdrawn.checked_add(amount).ok_or(Errors::Overflow)?;
+        Ok(())
+    }
+
+    // moves funds out of the balance without withdrawing them from the vault, e.g. into an escrow
+    pub fn debit_unlocked(&mut self, amount: u64, lock: &Option<VestingLock>, now: i64) -> Result<()> {
+        if amount > self.amount {
+            return Err(Errors::InsufficientBalance.into());
+        }
+        if amount > self.withdrawable(lock, now) {
+            return Err(Errors::FundsLocked.into());
+        }
+        self.debit(amount)
+    }
+
+    pub fn credit(&mut self, amount: u64) -> Result<()> {
+        self.amount = self.amount.checked_add(amount).ok_or(Errors::Overflow)?;
+        Ok(())
+    }
+
+    pub fn debit(&mut self, amount: u64) -> Result<()> {
+        self.amount = self.amount.checked_sub(amount).ok_or(Errors::InsufficientBalance)?;
+        Ok(())
+    }
+}
+
+// tokens pooled against receipt shares. Assets are tracked here rather than read from the share vault,
+// so tokens sent to the vault directly cannot move the share price.
+#[account]
+pub struct SharePool {
+    pub deposit_account: Pubkey,
+    pub mint: Pubkey,
+    pub assets: u64,
+}
+
+impl SharePool {
+    const LEN: usize = 8 + 32 + 32 + 8;
+    // a virtual share and asset price an empty pool at 1:1 and keep the first depositor from setting the price
+    const VIRTUAL_SHARES: u128 = 1;
+    const VIRTUAL_ASSETS: u128 = 1;
+
+    pub fn shares_for(&self, amount: u64, share_supply: u64) -> Result<u64> {
+        let shares = amount as u128 * (share_supply as u128 + Self::VIRTUAL_SHARES)
+            / (self.assets as u128 + Self::VIRTUAL_ASSETS);
+        u64::try_from(shares).map_err(|_| Errors::Overflow.into())
+    }
+
+    pub fn assets_for(&self, shares: u64, share_supply: u64) -> u64 {
+        let amount = shares as u128 * (self.assets as u128 + Self::VIRTUAL_ASSETS)
+            / (share_supply as u128 + Self::VIRTUAL_SHARES);
+        // rounds down, so never more than the pool holds
+        (amount as u64).min(self.assets)
+    }
+}
+
+// NFTs held by a deposit vault
+#[account]
+pub struct NftInventory {
+    pub deposit_account: Pubkey,
+    pub nfts: Vec<Pubkey>, // mints
+}
+
+impl NftInventory {
+    const MAX_NFTS: usize = 64;
+    const LEN: usize = 8 + 32 + 4 + 32 * Self::MAX_NFTS;
+
+    pub fn add(&mut self, mint: Pubkey) -> Result<()> {
+        if self.nfts.contains(&mint) {
+            return Ok(());
+        }
+        if self.nfts.len() >= Self::MAX_NFTS {
+            return Err(Errors::NftInventoryFull.into());
+        }
+        self.nfts.push(mint);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, mint: &Pubkey) -> Result<()> {
+        let index = self
+            .nfts
+            .iter()
+            .position(|nft| nft == mint)
+            .ok_or(Errors::NftNotInVault)?;
+        self.nfts.swap_remove(index);
+        Ok(())
+    }
+}
+
+// a market created through this program
+#[account]
+pub struct MarketInfo {
+    pub market: Pubkey,
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+    pub coin_lot_size: u64,
+    pub pc_lot_size: u64,
+    pub creator: Pubkey,
+}
+
+impl MarketInfo {
+    const LEN: usize = 8 + 32 * 4 + 8 + 8;
+}
+
 const OPTION_PUBKEY_LEN: usize = 1 + 32;
 
+// serum_dex OpenOrders plus its 5 byte head and 7 byte tail padding
+const OPEN_ORDERS_LEN: usize = 3228;
+
 impl Limit {
-    const LEN: usize = OPTION_PUBKEY_LEN * 2 + Asset::LEN * 2 + 8;
+    const LEN: usize = 8 + 32 + OPTION_PUBKEY_LEN * 2 + Asset::LEN * 2 + 8 + 8 + 1 + 1;
 }
 
 impl Asset {
-    const LEN: usize = 32 + OPTION_PUBKEY_LEN * 2;
-}
-
-pub fn mint_nft_and_create_metadata<'info>(
-    ctx: Context<MintftAndCreateMetadata<'info>>,
-    mint: &AccountInfo<'info>,
-    mint_auth: &AccountInfo<'info>,
-    data: DataV2,
-) -> Result<()> {
-    //let mint = create_mint()?;
-    let mint_cpi = InitializeMint2 {
-        mint: mint.to_account_info(),
-    };
-    let cpi = CpiContext::new(ctx.accounts.token_program.to_account_info(), mint_cpi);
-
-    initialize_mint2(cpi, 0, mint_auth.key, Some(mint_auth.key))?;
-
-    let meta_data_cpi = CreateMetadataAccountsV3 {
-        metadata: ctx.accounts.metadata.to_account_info(),
-        mint: mint.to_account_info(),
-        mint_authority: mint.to_account_info(),
-        payer: mint.to_account_info(),
-        update_authority: mint.to_account_info(),
-        system_program: ctx.accounts.system_program.to_account_info(),
-        rent: ctx.accounts.rent.to_account_info(),
-    };
-    let cpi = CpiContext::new(ctx.accounts.token_program.to_account_info(), meta_data_cpi);
-
-    let data = DataV2 {
-        name: "Placeholder".to_string(),
-        symbol: "PLC".to_string(),
-        uri: "localhost".to_string(),
-        seller_fee_basis_points: 100,
-        creators: None,
-        collection: None,
-        uses: None,
-    };
-
-    let meta_out = create_metadata_accounts_v3(cpi, data, true, true, None)?;
-
-    /*let master_edition_cpi = CreateMasterEditionV3 {
-        metadata: ctx.accounts.metadata.to_account_info(),
-        edition: ctx.accounts.edition.to_account_info(),
-        mint: mint.to_account_info(),
-        mint_authority: mint.to_account_info(),
-        payer: mint.to_account_info(),
-        update_authority: mint.to_account_info(),
-        system_program: ctx.accounts.system_program.to_account_info(),
-        rent: ctx.accounts.rent.to_account_info(),
-        token_program: ctx.accounts.token_program.to_account_info(),
-    };
-    let cpi = CpiContext::new(ctx.accounts.token_program.to_account_info(), meta_data_cpi);
-
-
-    anchor_spl::metadata::create_master_edition_v3(master_edition_cpi, Some(1))?;*/
-    Ok(())
+    const LEN: usize = 1 + OPTION_PUBKEY_LEN * 2;
+}
+
+// creator of a minted NFT, `share` is its percentage of the royalties
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct NftCreator {
+    pub address: Pubkey,
+    pub share: u8,
+}
+
+// serum_dex order parameters are not borsh serializable, so instructions take these mirrors instead
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq)]
+pub enum OrderSide {
+    Bid,
+    Ask,
+}
+
+impl From<OrderSide> for Side {
+    fn from(side: OrderSide) -> Self {
+        match side {
+            OrderSide::Bid => Side::Bid,
+            OrderSide::Ask => Side::Ask,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq)]
+pub enum DexOrderType {
+    Limit,
+    ImmediateOrCancel,
+    PostOnly,
+}
+
+impl From<DexOrderType> for OrderType {
+    fn from(order_type: DexOrderType) -> Self {
+        match order_type {
+            DexOrderType::Limit => OrderType::Limit,
+            DexOrderType::ImmediateOrCancel => OrderType::ImmediateOrCancel,
+            DexOrderType::PostOnly => OrderType::PostOnly,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq)]
+pub enum OrderSelfTradeBehavior {
+    DecrementTake,
+    CancelProvide,
+    AbortTransaction,
+}
+
+impl From<OrderSelfTradeBehavior> for SelfTradeBehavior {
+    fn from(behavior: OrderSelfTradeBehavior) -> Self {
+        match behavior {
+            OrderSelfTradeBehavior::DecrementTake => SelfTradeBehavior::DecrementTake,
+            OrderSelfTradeBehavior::CancelProvide => SelfTradeBehavior::CancelProvide,
+            OrderSelfTradeBehavior::AbortTransaction => SelfTradeBehavior::AbortTransaction,
+        }
+    }
 }
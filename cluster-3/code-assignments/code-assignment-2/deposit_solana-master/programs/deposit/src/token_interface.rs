@@ -0,0 +1,198 @@
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::token;
+use solana_program::{program::invoke_signed, program_pack::Pack};
+use spl_token_2022::{
+    extension::{
+        transfer_fee::{self, TransferFeeAmount},
+        ExtensionType, StateWithExtensions,
+    },
+    state::{Account as TokenAccountState, Mint as MintState},
+};
+
+use crate::error::Errors;
+
+// Helpers for instructions that accept mints of either the legacy token program or Token-2022. The
+// base layouts of both programs are the same, so Token-2022 unpacking reads legacy accounts too.
+
+pub fn check_token_program(token_program: &AccountInfo) -> Result<()> {
+    if *token_program.key != token::ID && *token_program.key != spl_token_2022::ID {
+        return Err(Errors::InvalidTokenProgram.into());
+    }
+    Ok(())
+}
+
+// decimals of a mint owned by `token_program`
+pub fn mint_decimals(mint: &AccountInfo, token_program: &AccountInfo) -> Result<u8> {
+    if mint.owner != token_program.key {
+        return Err(Errors::InvalidTokenProgram.into());
+    }
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<MintState>::unpack(&data)?;
+    Ok(mint.base.decimals)
+}
+
+// a zero decimal mint with a single token is treated as an NFT
+pub fn is_nft(mint: &AccountInfo, token_program: &AccountInfo) -> Result<bool> {
+    if mint.owner != token_program.key {
+        return Err(Errors::InvalidTokenProgram.into());
+    }
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<MintState>::unpack(&data)?;
+    Ok(mint.base.decimals == 0 && mint.base.supply == 1)
+}
+
+// mint of a token account owned by `token_program`
+pub fn token_mint(token_account: &AccountInfo, token_program: &AccountInfo) -> Result<Pubkey> {
+    if token_account.owner != token_program.key {
+        return Err(Errors::InvalidTokenAccount.into());
+    }
+    let data = token_account.try_borrow_data()?;
+    let account = StateWithExtensions::<TokenAccountState>::unpack(&data)?;
+    Ok(account.base.mint)
+}
+
+// balance of a token account of `mint`, optionally also checking its owner
+pub fn token_amount(
+    token_account: &AccountInfo,
+    mint: &Pubkey,
+    owner: Option<&Pubkey>,
+    token_program: &AccountInfo,
+) -> Result<u64> {
+    if token_account.owner != token_program.key {
+        return Err(Errors::InvalidTokenAccount.into());
+    }
+    let data = token_account.try_borrow_data()?;
+    let account = StateWithExtensions::<TokenAccountState>::unpack(&data)?;
+    if account.base.mint != *mint || owner.map_or(false, |owner| account.base.owner != *owner) {
+        return Err(Errors::InvalidTokenAccount.into());
+    }
+    Ok(account.base.amount)
+}
+
+// transfer_checked is required by Token-2022 for mints with extensions and works for legacy mints too
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_checked<'info>(
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    amount: u64,
+    decimals: u8,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let ix = spl_token_2022::instruction::transfer_checked(
+        token_program.key,
+        from.key,
+        mint.key,
+        to.key,
+        authority.key,
+        &[],
+        amount,
+        decimals,
+    )?;
+
+    invoke_signed(
+        &ix,
+        &[from.clone(), mint.clone(), to.clone(), authority.clone(), token_program.clone()],
+        signer_seeds,
+    )?;
+
+    Ok(())
+}
+
+// space of a token account of `mint`, including the account extensions the mint requires
+pub fn account_len(mint: &AccountInfo, token_program: &AccountInfo) -> Result<usize> {
+    if mint.owner != token_program.key {
+        return Err(Errors::InvalidTokenProgram.into());
+    }
+    if *token_program.key == token::ID {
+        return Ok(TokenAccountState::LEN);
+    }
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<MintState>::unpack(&data)?;
+    let extensions = ExtensionType::get_required_init_account_extensions(&mint.get_extension_types()?);
+    Ok(ExtensionType::get_account_len::<TokenAccountState>(&extensions))
+}
+
+// creates a token account of `mint` owned by `owner` at a PDA of this program, signed by `signer_seeds`
+#[allow(clippy::too_many_arguments)]
+pub fn create_token_account<'info>(
+    token_program: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    owner: &Pubkey,
+    system_program: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let space = account_len(mint, token_program)?;
+    let lamports = Rent::get()?.minimum_balance(space);
+
+    let cpi_accounts = system_program::CreateAccount {
+        from: payer.clone(),
+        to: account.clone(),
+    };
+
+    let cpi = CpiContext::new_with_signer(system_program.clone(), cpi_accounts, signer_seeds);
+
+    system_program::create_account(cpi, lamports, space as u64, token_program.key)?;
+
+    let ix = spl_token_2022::instruction::initialize_account3(token_program.key, account.key, mint.key, owner)?;
+
+    invoke_signed(&ix, &[account.clone(), mint.clone(), token_program.clone()], &[])?;
+
+    Ok(())
+}
+
+// Token-2022 withholds transfer fees in the receiving account, which cannot be closed until they are
+// harvested to the mint
+pub fn harvest_withheld<'info>(
+    token_program: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+) -> Result<()> {
+    if *token_program.key != spl_token_2022::ID {
+        return Ok(());
+    }
+    let withheld = {
+        let data = account.try_borrow_data()?;
+        let account = StateWithExtensions::<TokenAccountState>::unpack(&data)?;
+        account
+            .get_extension::<TransferFeeAmount>()
+            .map_or(0, |fee| u64::from(fee.withheld_amount))
+    };
+    if withheld == 0 {
+        return Ok(());
+    }
+
+    let ix = transfer_fee::instruction::harvest_withheld_tokens_to_mint(token_program.key, mint.key, &[account.key])?;
+
+    invoke_signed(&ix, &[mint.clone(), account.clone(), token_program.clone()], &[])?;
+
+    Ok(())
+}
+
+pub fn close_account<'info>(
+    token_program: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let ix = spl_token_2022::instruction::close_account(
+        token_program.key,
+        account.key,
+        destination.key,
+        authority.key,
+        &[],
+    )?;
+
+    invoke_signed(
+        &ix,
+        &[account.clone(), destination.clone(), authority.clone(), token_program.clone()],
+        signer_seeds,
+    )?;
+
+    Ok(())
+}
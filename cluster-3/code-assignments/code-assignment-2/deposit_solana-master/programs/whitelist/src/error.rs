@@ -0,0 +1,11 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum Errors {
+    #[msg("Address is not whitelisted.")]
+    NotWhitelisted,
+    #[msg("Bump seed of a PDA could not be found.")]
+    MissingBump,
+    #[msg("An overflow occurs.")]
+    Overflow,
+}
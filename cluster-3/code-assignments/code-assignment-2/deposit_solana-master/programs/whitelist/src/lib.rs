@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
-use deposit::DepositBase;
-use std::mem::size_of;
+
+pub mod error;
+use error::Errors;
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
@@ -9,54 +10,112 @@ pub mod whitelist {
     use super::*;
 
     pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+        let whitelist_account = &mut ctx.accounts.whitelist_account;
+        whitelist_account.whitelist_auth = *ctx.accounts.whitelist_auth.key;
+        whitelist_account.entry_count = 0;
         Ok(())
     }
 
-    pub fn add_to_whitelist(ctx: Context<AddtoWhiteList>) -> Result<()> {
+    pub fn add_to_whitelist(ctx: Context<AddtoWhiteList>, address: Pubkey) -> Result<()> {
+        let entry = &mut ctx.accounts.entry;
+        entry.whitelist = ctx.accounts.whitelist_account.key();
+        entry.address = address;
+        entry.bump = *ctx.bumps.get("entry").ok_or(Errors::MissingBump)?;
+
+        let whitelist_account = &mut ctx.accounts.whitelist_account;
+        whitelist_account.entry_count = whitelist_account.entry_count.checked_add(1).ok_or(Errors::Overflow)?;
+
+        Ok(())
+    }
+
+    pub fn remove_from_whitelist(ctx: Context<RemoveFromWhitelist>, _address: Pubkey) -> Result<()> {
+        let whitelist_account = &mut ctx.accounts.whitelist_account;
+        whitelist_account.entry_count = whitelist_account.entry_count.saturating_sub(1);
+
         Ok(())
     }
 
-    pub fn is_whitelisted(ctx: Context<IsWhitelisted>) -> Result<()> {
+    // succeeds only when `address` is whitelisted, so other programs can gate instructions on it via CPI
+    pub fn is_whitelisted(ctx: Context<IsWhitelisted>, address: Pubkey) -> Result<()> {
+        let entry = &ctx.accounts.entry;
+
+        if entry.owner != &ID || entry.data_is_empty() {
+            return Err(Errors::NotWhitelisted.into());
+        }
+
+        let data = entry.try_borrow_data()?;
+        let entry = WhitelistEntry::try_deserialize(&mut &data[..])?;
+        if entry.whitelist != ctx.accounts.whitelist_account.key() || entry.address != address {
+            return Err(Errors::NotWhitelisted.into());
+        }
+
         Ok(())
     }
 }
 
 #[derive(Accounts)]
-pub struct Initialize {
-    #[account(init, payer = signer, space = size_of::<WhitelistBase>() )]
-    pub signer: Signer<'info>,
+pub struct Initialize<'info> {
+    #[account(init, payer = whitelist_auth, space = WhitelistBase::LEN)]
+    pub whitelist_account: Account<'info, WhitelistBase>,
+    #[account(mut)]
+    pub whitelist_auth: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
+#[instruction(address: Pubkey)]
 pub struct AddtoWhiteList<'info> {
-    #[account(mut, has_one = deposit_auth)]
-    pub deposit_account: Account<'info, WhitelistBase>,
+    #[account(mut, has_one = whitelist_auth)]
+    pub whitelist_account: Account<'info, WhitelistBase>,
+    #[account(init, payer = whitelist_auth, space = WhitelistEntry::LEN,
+        seeds = [b"entry", whitelist_account.key().as_ref(), address.as_ref()], bump
+    )]
+    pub entry: Account<'info, WhitelistEntry>,
     #[account(mut)]
-    pub whitelist_account: Account<'info>,
-    #[account(seeds = [b"auth", deposit_account.key().as_ref(), whitelist_account.key().as_ref()], bump = deposit_account.auth_bump)]
-    pub pda_auth: UncheckedAccount<'info>,
-    #[account(mut)]
-    pub deposit_auth: Signer<'info>,
+    pub whitelist_auth: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct IsWhitelisted<'info> {
-    #[account(mut)]
-    pub deposit_account: Account<'info, WhitelistBase>,
-    #[account(mut, has_one = deposit_auth)]
-    pub whitelist_account: Account<'info>,
-    #[account(seeds = [b"auth", deposit_account.key().as_ref(), whitelist_account.key().as_ref()], bump = deposit_account.auth_bump)]
-    pub pda_auth: UncheckedAccount<'info>,
+#[instruction(address: Pubkey)]
+pub struct RemoveFromWhitelist<'info> {
+    #[account(mut, has_one = whitelist_auth)]
+    pub whitelist_account: Account<'info, WhitelistBase>,
+    #[account(mut, close = whitelist_auth,
+        seeds = [b"entry", whitelist_account.key().as_ref(), address.as_ref()], bump = entry.bump
+    )]
+    pub entry: Account<'info, WhitelistEntry>,
     #[account(mut)]
-    pub deposit_auth: Signer<'info>,
-    pub system_program: Program<'info, System>,
+    pub whitelist_auth: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct IsWhitelisted<'info> {
+    pub whitelist_account: Account<'info, WhitelistBase>,
+    #[account(seeds = [b"entry", whitelist_account.key().as_ref(), address.as_ref()], bump)]
+    /// CHECK: may not exist when the address is not whitelisted, checked in the handler.
+    pub entry: UncheckedAccount<'info>,
 }
 
 #[account]
 pub struct WhitelistBase {
-    pub deposit_auth: Pubkey,
     pub whitelist_auth: Pubkey,
-    pub auth_bump: u8,
-}
\ No newline at end of file
+    pub entry_count: u64,
+}
+
+impl WhitelistBase {
+    const LEN: usize = 8 + 32 + 8;
+}
+
+// one per whitelisted address
+#[account]
+pub struct WhitelistEntry {
+    pub whitelist: Pubkey,
+    pub address: Pubkey,
+    pub bump: u8,
+}
+
+impl WhitelistEntry {
+    const LEN: usize = 8 + 32 + 32 + 1;
+}
@@ -1,7 +1,7 @@
 import * as anchor from "@project-serum/anchor";
 import { Program } from "@project-serum/anchor";
 import { Deposit } from "../target/types/deposit";
-import { ASSOCIATED_TOKEN_PROGRAM_ID, createMint, getAssociatedTokenAddressSync, getMint, getOrCreateAssociatedTokenAccount, mintToChecked, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { ASSOCIATED_TOKEN_PROGRAM_ID, createInitializeMintInstruction, createInitializeTransferFeeConfigInstruction, createMint, ExtensionType, getAssociatedTokenAddressSync, getMint, getMintLen, getOrCreateAssociatedTokenAccount, mintToChecked, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID, transfer } from "@solana/spl-token";
 import { keypairIdentity, Metaplex } from "@metaplex-foundation/js";
 import { execSync } from "child_process";
 import { assert } from "chai";
@@ -17,6 +17,7 @@ describe("deposit", () => {
   const deposit_auth = anchor.web3.Keypair.generate();
   let mint = anchor.web3.Keypair.generate();
   let usdc_auth = anchor.web3.Keypair.generate();
+  let ask_mint = anchor.web3.Keypair.generate();
 
   const metaplex = Metaplex.make(provider.connection).use(keypairIdentity(deposit_auth));
 
@@ -33,6 +34,21 @@ describe("deposit", () => {
     ],
     program.programId);
 
+  let [native_balance] = anchor.web3.PublicKey.findProgramAddressSync(
+    [anchor.utils.bytes.utf8.encode("balance"),
+    deposit_account.publicKey.toBuffer(),
+    deposit_auth.publicKey.toBuffer()
+    ],
+    program.programId);
+
+  let [spl_balance] = anchor.web3.PublicKey.findProgramAddressSync(
+    [anchor.utils.bytes.utf8.encode("balance"),
+    deposit_account.publicKey.toBuffer(),
+    deposit_auth.publicKey.toBuffer(),
+    mint.publicKey.toBuffer()
+    ],
+    program.programId);
+
   /*execSync(
     `anchor idl init --filepath target/idl/deposit.json ${program.programId}`,
     { stdio: "inherit" }
@@ -57,6 +73,7 @@ describe("deposit", () => {
       .accounts({
         depositAccount: deposit_account.publicKey,
         pdaAuth: pda_auth,
+        solVault: sol_vault,
         depositAuth: deposit_auth.publicKey,
         systemProgram: anchor.web3.SystemProgram.programId,
       }).signers([deposit_account, deposit_auth]).rpc();
@@ -65,16 +82,36 @@ describe("deposit", () => {
 
     let result = await program.account.depositBase.fetch(deposit_account.publicKey);
     console.log(result);
+    assert.equal(result.authBump, pda_bump);
+    assert.equal(result.solVaultBump, sol_bump);
+  });
+
+  it("Fails to withdraw native SOL before any deposit", async () => {
+    try {
+      await program.methods.withdrawNative(new anchor.BN(1)).accounts({
+        depositAccount: deposit_account.publicKey,
+        pdaAuth: pda_auth,
+        solVault: sol_vault,
+        depositor: deposit_auth.publicKey,
+        balance: native_balance,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }).signers([deposit_auth]).rpc();
+      assert.fail("withdrawal before any deposit succeeded");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "AccountNotInitialized");
+    }
   });
 
   xit("Deposits native SOL", async () => {
     const deposit_amount = new anchor.BN(25 * anchor.web3.LAMPORTS_PER_SOL);
-    const deposit_native_tx = await program.methods.depositNative(deposit_amount)
+    const deposit_native_tx = await program.methods.depositNative(deposit_amount, null)
       .accounts({
         depositAccount: deposit_account.publicKey,
         pdaAuth: pda_auth,
         solVault: sol_vault,
-        depositAuth: deposit_auth.publicKey,
+        depositor: deposit_auth.publicKey,
+        beneficiary: deposit_auth.publicKey,
+        balance: native_balance,
         systemProgram: anchor.web3.SystemProgram.programId,
       }).signers([deposit_auth]).rpc();
 
@@ -84,6 +121,9 @@ describe("deposit", () => {
     let result = await program.account.depositBase.fetch(deposit_account.publicKey);
     console.log(result);
 
+    let balance = await program.account.userBalance.fetch(native_balance);
+    assert.equal(balance.amount.toNumber(), 25 * anchor.web3.LAMPORTS_PER_SOL);
+
   });
 
   xit("Withdraws native SOL", async () => {
@@ -94,7 +134,8 @@ describe("deposit", () => {
         depositAccount: deposit_account.publicKey,
         pdaAuth: pda_auth,
         solVault: sol_vault,
-        depositAuth: deposit_auth.publicKey,
+        depositor: deposit_auth.publicKey,
+        balance: native_balance,
         systemProgram: anchor.web3.SystemProgram.programId,
       }).signers([deposit_auth]).rpc();
 
@@ -135,13 +176,15 @@ describe("deposit", () => {
     let to_token_acct = getAssociatedTokenAddressSync(mint.publicKey, pda_auth, true, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
     let from_token_acct = getAssociatedTokenAddressSync(mint.publicKey, deposit_auth.publicKey, false, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
 
-    let deposit_spl_tx = await program.methods.depositSpl(new anchor.BN(25e6)).accounts(
+    let deposit_spl_tx = await program.methods.depositSpl(new anchor.BN(25e6), null).accounts(
       {
         depositAccount: deposit_account.publicKey,
         pdaAuth: pda_auth,
-        depositAuth: deposit_auth.publicKey,
+        depositor: deposit_auth.publicKey,
         fromTokenAcct: from_token_acct,
+        beneficiary: deposit_auth.publicKey,
         toTokenAcct: to_token_acct,
+        balance: spl_balance,
         tokenMint: mint.publicKey,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         tokenProgram: TOKEN_PROGRAM_ID,
@@ -161,9 +204,10 @@ describe("deposit", () => {
     let withdraw_spl_tx = await program.methods.withdrawSpl(new anchor.BN(1e6)).accounts({
       depositAccount: deposit_account.publicKey,
       pdaAuth: pda_auth,
-      depositAuth: deposit_auth.publicKey,
+      depositor: deposit_auth.publicKey,
       fromTokenAcct: from_token_acct,
       toTokenAcct: to_token_acct,
+      balance: spl_balance,
       tokenMint: mint.publicKey,
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       tokenProgram: TOKEN_PROGRAM_ID,
@@ -174,4 +218,681 @@ describe("deposit", () => {
 
   });
 
-});;
\ No newline at end of file
+  it("Records only what arrives of a Token-2022 deposit with a transfer fee", async () => {
+    const fee_mint = anchor.web3.Keypair.generate();
+    const fee_basis_points = 100;
+
+    const mint_len = getMintLen([ExtensionType.TransferFeeConfig]);
+    const lamports = await provider.connection.getMinimumBalanceForRentExemption(mint_len);
+
+    const create_mint_tx = new anchor.web3.Transaction().add(
+      anchor.web3.SystemProgram.createAccount({
+        fromPubkey: deposit_auth.publicKey,
+        newAccountPubkey: fee_mint.publicKey,
+        space: mint_len,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      createInitializeTransferFeeConfigInstruction(fee_mint.publicKey, usdc_auth.publicKey, usdc_auth.publicKey, fee_basis_points, BigInt(1e9), TOKEN_2022_PROGRAM_ID),
+      createInitializeMintInstruction(fee_mint.publicKey, 6, usdc_auth.publicKey, null, TOKEN_2022_PROGRAM_ID),
+    );
+    await anchor.web3.sendAndConfirmTransaction(provider.connection, create_mint_tx, [deposit_auth, fee_mint]);
+
+    let from_token_acct = await getOrCreateAssociatedTokenAccount(provider.connection, deposit_auth, fee_mint.publicKey, deposit_auth.publicKey, false, undefined, undefined, TOKEN_2022_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
+    await mintToChecked(provider.connection, deposit_auth, fee_mint.publicKey, from_token_acct.address, usdc_auth, 10e6, 6, [], undefined, TOKEN_2022_PROGRAM_ID);
+
+    let [fee_balance] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("balance"),
+      deposit_account.publicKey.toBuffer(),
+      deposit_auth.publicKey.toBuffer(),
+      fee_mint.publicKey.toBuffer()
+      ],
+      program.programId);
+
+    await program.methods.depositSpl(new anchor.BN(10e6), null).accounts({
+      depositAccount: deposit_account.publicKey,
+      pdaAuth: pda_auth,
+      depositor: deposit_auth.publicKey,
+      fromTokenAcct: from_token_acct.address,
+      beneficiary: deposit_auth.publicKey,
+      toTokenAcct: getAssociatedTokenAddressSync(fee_mint.publicKey, pda_auth, true, TOKEN_2022_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID),
+      balance: fee_balance,
+      tokenMint: fee_mint.publicKey,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    }).signers([deposit_auth]).rpc();
+
+    // 1% of the deposit is withheld by the mint
+    let balance = await program.account.userBalance.fetch(fee_balance);
+    assert.equal(balance.amount.toNumber(), 10e6 - 10e6 * fee_basis_points / 10_000);
+  });
+
+  it("Mints and redeems shares in proportion to the pool", async () => {
+    const pool_mint = anchor.web3.Keypair.generate();
+    const depositor = anchor.web3.Keypair.generate();
+
+    let res = await provider.connection.requestAirdrop(depositor.publicKey, 10 * anchor.web3.LAMPORTS_PER_SOL);
+    let latestBlockHash = await provider.connection.getLatestBlockhash();
+    await provider.connection.confirmTransaction({
+      blockhash: latestBlockHash.blockhash,
+      lastValidBlockHeight: latestBlockHash.lastValidBlockHeight,
+      signature: res,
+    });
+
+    await createMint(provider.connection, deposit_auth, usdc_auth.publicKey, null, 6, pool_mint, null, TOKEN_PROGRAM_ID);
+
+    let auth_acct = await getOrCreateAssociatedTokenAccount(provider.connection, deposit_auth, pool_mint.publicKey, deposit_auth.publicKey, false, undefined, undefined, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
+    let depositor_acct = await getOrCreateAssociatedTokenAccount(provider.connection, deposit_auth, pool_mint.publicKey, depositor.publicKey, false, undefined, undefined, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
+    await mintToChecked(provider.connection, deposit_auth, pool_mint.publicKey, auth_acct.address, usdc_auth, 200e6, 6, [], undefined, TOKEN_PROGRAM_ID);
+    await mintToChecked(provider.connection, deposit_auth, pool_mint.publicKey, depositor_acct.address, usdc_auth, 100e6, 6, [], undefined, TOKEN_PROGRAM_ID);
+
+    let [share_vault] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("share_vault"), deposit_account.publicKey.toBuffer(), pool_mint.publicKey.toBuffer()],
+      program.programId);
+    let [share_mint] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("share_mint"), deposit_account.publicKey.toBuffer(), pool_mint.publicKey.toBuffer()],
+      program.programId);
+    let [share_pool] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("share_pool"), deposit_account.publicKey.toBuffer(), pool_mint.publicKey.toBuffer()],
+      program.programId);
+
+    const depositShares = (signer: anchor.web3.Keypair, from: anchor.web3.PublicKey, amount: number) =>
+      program.methods.depositShares(new anchor.BN(amount)).accounts({
+        depositAccount: deposit_account.publicKey,
+        pdaAuth: pda_auth,
+        depositor: signer.publicKey,
+        shareVault: share_vault,
+        shareMint: share_mint,
+        sharePool: share_pool,
+        shareTokenAcct: getAssociatedTokenAddressSync(share_mint, signer.publicKey, false, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID),
+        fromTokenAcct: from,
+        tokenMint: pool_mint.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      }).signers([signer]).rpc();
+
+    // a minimal first deposit followed by a large donation to the vault
+    await depositShares(deposit_auth, auth_acct.address, 1);
+    await transfer(provider.connection, deposit_auth, auth_acct.address, share_vault, deposit_auth, 100e6, [], undefined, TOKEN_PROGRAM_ID);
+
+    // the donation does not change the share price for the next depositor
+    await depositShares(depositor, depositor_acct.address, 50e6);
+
+    let depositor_shares = getAssociatedTokenAddressSync(share_mint, depositor.publicKey, false, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
+    let shares = (await provider.connection.getTokenAccountBalance(depositor_shares)).value.amount;
+    assert.equal(Number(shares), 50e6);
+
+    let pool = await program.account.sharePool.fetch(share_pool);
+    assert.equal(pool.assets.toNumber(), 50e6 + 1);
+
+    // redeeming half of the shares returns half of the deposit
+    await program.methods.redeemShares(new anchor.BN(25e6)).accounts({
+      depositAccount: deposit_account.publicKey,
+      pdaAuth: pda_auth,
+      depositor: depositor.publicKey,
+      shareVault: share_vault,
+      shareMint: share_mint,
+      sharePool: share_pool,
+      shareTokenAcct: depositor_shares,
+      toTokenAcct: depositor_acct.address,
+      tokenMint: pool_mint.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    }).signers([depositor]).rpc();
+
+    let returned = (await provider.connection.getTokenAccountBalance(depositor_acct.address)).value.amount;
+    assert.equal(Number(returned), 75e6);
+  });
+
+  let [limit] = anchor.web3.PublicKey.findProgramAddressSync(
+    [anchor.utils.bytes.utf8.encode("limit"),
+    deposit_account.publicKey.toBuffer(),
+    mint.publicKey.toBuffer()
+    ],
+    program.programId);
+
+  let [limit_escrow] = anchor.web3.PublicKey.findProgramAddressSync(
+    [anchor.utils.bytes.utf8.encode("limit_escrow"),
+    limit.toBuffer()
+    ],
+    program.programId);
+
+  xit("Creates a limit", async () => {
+    let vault_asset_acct = getAssociatedTokenAddressSync(mint.publicKey, pda_auth, true, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
+
+    let create_limit_tx = await program.methods.createLimit({ spl: {} }, new anchor.BN(5e6), new anchor.BN(2)).accounts({
+      depositAccount: deposit_account.publicKey,
+      pdaAuth: pda_auth,
+      depositAuth: deposit_auth.publicKey,
+      limit: limit,
+      limitEscrow: limit_escrow,
+      vaultAssetAcct: vault_asset_acct,
+      ownerBalance: spl_balance,
+      nftInventory: null,
+      assetMint: mint.publicKey,
+      askMint: ask_mint.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      askTokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    }).signers([deposit_auth]).rpc();
+
+    console.log(create_limit_tx);
+
+    let result = await program.account.limit.fetch(limit);
+    assert.equal(result.assetAmount.toNumber(), 5e6);
+  });
+
+  xit("Updates a limit", async () => {
+    let update_limit_tx = await program.methods.updateLimit(new anchor.BN(3)).accounts({
+      depositAccount: deposit_account.publicKey,
+      limit: limit,
+      depositAuth: deposit_auth.publicKey,
+    }).signers([deposit_auth]).rpc();
+
+    console.log(update_limit_tx);
+
+    let result = await program.account.limit.fetch(limit);
+    assert.equal(result.askPricePerAsset.toNumber(), 3);
+  });
+
+  xit("Removes a limit", async () => {
+    let vault_asset_acct = getAssociatedTokenAddressSync(mint.publicKey, pda_auth, true, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
+
+    let remove_limit_tx = await program.methods.removeLimit().accounts({
+      depositAccount: deposit_account.publicKey,
+      pdaAuth: pda_auth,
+      depositAuth: deposit_auth.publicKey,
+      limit: limit,
+      limitEscrow: limit_escrow,
+      vaultAssetAcct: vault_asset_acct,
+      ownerBalance: spl_balance,
+      nftInventory: null,
+      assetMint: mint.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    }).signers([deposit_auth]).rpc();
+
+    console.log(remove_limit_tx);
+
+    assert.isNull(await provider.connection.getAccountInfo(limit));
+  });
+
+  const metadata_program = new anchor.web3.PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+  let nft_mint = anchor.web3.Keypair.generate();
+
+  let [metadata] = anchor.web3.PublicKey.findProgramAddressSync(
+    [anchor.utils.bytes.utf8.encode("metadata"),
+    metadata_program.toBuffer(),
+    nft_mint.publicKey.toBuffer()
+    ],
+    metadata_program);
+
+  let [nft_inventory] = anchor.web3.PublicKey.findProgramAddressSync(
+    [anchor.utils.bytes.utf8.encode("nft_inventory"),
+    deposit_account.publicKey.toBuffer()
+    ],
+    program.programId);
+
+  xit("Mints an NFT into the vault", async () => {
+
+    let [edition] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("metadata"),
+      metadata_program.toBuffer(),
+      nft_mint.publicKey.toBuffer(),
+      anchor.utils.bytes.utf8.encode("edition")
+      ],
+      metadata_program);
+
+    let vault_nft_acct = getAssociatedTokenAddressSync(nft_mint.publicKey, pda_auth, true, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
+
+    let mint_nft_tx = await program.methods.mintNft("Vault 0001", "VLT", "https://arweave.net/vault", 500, [{ address: deposit_auth.publicKey, share: 100 }]).accounts({
+      depositAccount: deposit_account.publicKey,
+      pdaAuth: pda_auth,
+      depositAuth: deposit_auth.publicKey,
+      mint: nft_mint.publicKey,
+      vaultNftAcct: vault_nft_acct,
+      metadata: metadata,
+      edition: edition,
+      nftInventory: nft_inventory,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      metadataProgram: metadata_program,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    }).signers([deposit_auth, nft_mint]).rpc();
+
+    console.log(mint_nft_tx);
+
+    let nft_mint_info = await getMint(provider.connection, nft_mint.publicKey, null, TOKEN_PROGRAM_ID);
+    assert.equal(Number(nft_mint_info.supply), 1);
+    assert.ok(nft_mint_info.mintAuthority.equals(edition));
+
+    let inventory = await program.account.nftInventory.fetch(nft_inventory);
+    assert.ok(inventory.nfts.some((nft) => nft.equals(nft_mint.publicKey)));
+  });
+
+  xit("Withdraws an NFT from the vault", async () => {
+    let withdraw_nft_tx = await program.methods.withdrawNft().accounts({
+      depositAccount: deposit_account.publicKey,
+      pdaAuth: pda_auth,
+      depositAuth: deposit_auth.publicKey,
+      toTokenAcct: getAssociatedTokenAddressSync(nft_mint.publicKey, deposit_auth.publicKey, false, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID),
+      fromTokenAcct: getAssociatedTokenAddressSync(nft_mint.publicKey, pda_auth, true, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID),
+      nftMint: nft_mint.publicKey,
+      nftInventory: nft_inventory,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    }).signers([deposit_auth]).rpc();
+
+    console.log(withdraw_nft_tx);
+
+    let inventory = await program.account.nftInventory.fetch(nft_inventory);
+    assert.isFalse(inventory.nfts.some((nft) => nft.equals(nft_mint.publicKey)));
+  });
+
+  xit("Rejects an NFT outside the vault collection", async () => {
+    const collection = anchor.web3.Keypair.generate().publicKey;
+
+    let set_collection_tx = await program.methods.setNftCollection(collection).accounts({
+      depositAccount: deposit_account.publicKey,
+      depositAuth: deposit_auth.publicKey,
+    }).signers([deposit_auth]).rpc();
+
+    console.log(set_collection_tx);
+
+    // the minted NFT is not a verified member of any collection
+    try {
+      await program.methods.depositNft().accounts({
+        depositAccount: deposit_account.publicKey,
+        pdaAuth: pda_auth,
+        depositAuth: deposit_auth.publicKey,
+        toTokenAcct: getAssociatedTokenAddressSync(nft_mint.publicKey, pda_auth, true, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID),
+        fromTokenAcct: getAssociatedTokenAddressSync(nft_mint.publicKey, deposit_auth.publicKey, false, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID),
+        nftMint: nft_mint.publicKey,
+        metadata: metadata,
+        nftInventory: nft_inventory,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        metadataProgram: metadata_program,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }).signers([deposit_auth]).rpc();
+      assert.fail("deposit of an NFT outside the collection succeeded");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidCollection");
+    }
+
+    try {
+      await program.methods.setNftCollection(anchor.web3.Keypair.generate().publicKey).accounts({
+        depositAccount: deposit_account.publicKey,
+        depositAuth: deposit_auth.publicKey,
+      }).signers([deposit_auth]).rpc();
+      assert.fail("vault collection was changed");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "CollectionAlreadySet");
+    }
+  });
+
+  it("Moves the owner's balance only through approved multisig transfers", async () => {
+    const vault = anchor.web3.Keypair.generate();
+    const owner = anchor.web3.Keypair.generate();
+    const signers = [anchor.web3.Keypair.generate(), anchor.web3.Keypair.generate(), anchor.web3.Keypair.generate()];
+    const recipient = anchor.web3.Keypair.generate().publicKey;
+
+    for (const wallet of [owner, ...signers]) {
+      let res = await provider.connection.requestAirdrop(wallet.publicKey, 10 * anchor.web3.LAMPORTS_PER_SOL);
+      let latestBlockHash = await provider.connection.getLatestBlockhash();
+      await provider.connection.confirmTransaction({
+        blockhash: latestBlockHash.blockhash,
+        lastValidBlockHeight: latestBlockHash.lastValidBlockHeight,
+        signature: res,
+      });
+    }
+
+    let [vault_auth] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("auth"), vault.publicKey.toBuffer()],
+      program.programId);
+    let [vault_sol] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("sol_vault"), vault_auth.toBuffer()],
+      program.programId);
+    let [owner_balance] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("balance"), vault.publicKey.toBuffer(), owner.publicKey.toBuffer()],
+      program.programId);
+    let [pending_transfer] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("pending_transfer"), vault.publicKey.toBuffer(), new anchor.BN(0).toArrayLike(Buffer, "le", 8)],
+      program.programId);
+
+    await program.methods.initialize().accounts({
+      depositAccount: vault.publicKey,
+      pdaAuth: vault_auth,
+      solVault: vault_sol,
+      depositAuth: owner.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    }).signers([vault, owner]).rpc();
+
+    const native_accounts = {
+      depositAccount: vault.publicKey,
+      pdaAuth: vault_auth,
+      solVault: vault_sol,
+      depositor: owner.publicKey,
+      beneficiary: owner.publicKey,
+      balance: owner_balance,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    };
+
+    await program.methods.depositNative(new anchor.BN(2 * anchor.web3.LAMPORTS_PER_SOL), null).accounts(native_accounts).signers([owner]).rpc();
+
+    await program.methods.setMultisig(signers.map((signer) => signer.publicKey), 2).accounts({
+      depositAccount: vault.publicKey,
+      depositAuth: owner.publicKey,
+    }).signers([owner]).rpc();
+
+    try {
+      await program.methods.withdrawNative(new anchor.BN(1)).accounts(native_accounts).signers([owner]).rpc();
+      assert.fail("direct withdrawal in multisig mode succeeded");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "MultisigRequired");
+    }
+
+    await program.methods.proposeTransfer({ spl: {} }, null, new anchor.BN(anchor.web3.LAMPORTS_PER_SOL), recipient).accounts({
+      depositAccount: vault.publicKey,
+      pendingTransfer: pending_transfer,
+      proposer: signers[0].publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    }).signers([signers[0]]).rpc();
+
+    const execute = () => program.methods.executeNativeTransfer().accounts({
+      depositAccount: vault.publicKey,
+      pdaAuth: vault_auth,
+      solVault: vault_sol,
+      pendingTransfer: pending_transfer,
+      balance: owner_balance,
+      to: recipient,
+      proposer: signers[0].publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    }).rpc();
+
+    // the proposal only carries the proposer's approval
+    try {
+      await execute();
+      assert.fail("transfer below the threshold was executed");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "NotEnoughApprovals");
+    }
+
+    const approve = (signer: anchor.web3.Keypair) => program.methods.approveTransfer().accounts({
+      depositAccount: vault.publicKey,
+      pendingTransfer: pending_transfer,
+      owner: signer.publicKey,
+    }).signers([signer]).rpc();
+
+    try {
+      await approve(signers[0]);
+      assert.fail("duplicate approval was counted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "AlreadyApproved");
+    }
+
+    await approve(signers[1]);
+    await execute();
+
+    assert.equal(await provider.connection.getBalance(recipient), anchor.web3.LAMPORTS_PER_SOL);
+    assert.isNull(await provider.connection.getAccountInfo(pending_transfer));
+
+    let balance = await program.account.userBalance.fetch(owner_balance);
+    assert.equal(balance.amount.toNumber(), anchor.web3.LAMPORTS_PER_SOL);
+
+    // a proposal the other owners will not approve can be cancelled so it does not keep the vault open
+    let [rejected_transfer] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("pending_transfer"), vault.publicKey.toBuffer(), new anchor.BN(1).toArrayLike(Buffer, "le", 8)],
+      program.programId);
+
+    await program.methods.proposeTransfer({ spl: {} }, null, new anchor.BN(anchor.web3.LAMPORTS_PER_SOL), recipient).accounts({
+      depositAccount: vault.publicKey,
+      pendingTransfer: rejected_transfer,
+      proposer: signers[0].publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    }).signers([signers[0]]).rpc();
+
+    await program.methods.cancelTransfer().accounts({
+      depositAccount: vault.publicKey,
+      pendingTransfer: rejected_transfer,
+      proposer: signers[0].publicKey,
+      owner: signers[2].publicKey,
+    }).signers([signers[2]]).rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(rejected_transfer));
+    let vault_state = await program.account.depositBase.fetch(vault.publicKey);
+    assert.equal(vault_state.openPositions.transfers, 0);
+  });
+
+  it("Closes a vault once the owner's balances are withdrawn, refunding other depositors", async () => {
+    const vault = anchor.web3.Keypair.generate();
+    const owner = anchor.web3.Keypair.generate();
+    const stranger = anchor.web3.Keypair.generate();
+
+    for (const wallet of [owner, stranger]) {
+      let res = await provider.connection.requestAirdrop(wallet.publicKey, 10 * anchor.web3.LAMPORTS_PER_SOL);
+      let latestBlockHash = await provider.connection.getLatestBlockhash();
+      await provider.connection.confirmTransaction({
+        blockhash: latestBlockHash.blockhash,
+        lastValidBlockHeight: latestBlockHash.lastValidBlockHeight,
+        signature: res,
+      });
+    }
+
+    let [vault_auth] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("auth"), vault.publicKey.toBuffer()],
+      program.programId);
+    let [vault_sol] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("sol_vault"), vault_auth.toBuffer()],
+      program.programId);
+    let [owner_balance] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("balance"), vault.publicKey.toBuffer(), owner.publicKey.toBuffer()],
+      program.programId);
+    let [stranger_balance] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("balance"), vault.publicKey.toBuffer(), stranger.publicKey.toBuffer()],
+      program.programId);
+
+    await program.methods.initialize().accounts({
+      depositAccount: vault.publicKey,
+      pdaAuth: vault_auth,
+      solVault: vault_sol,
+      depositAuth: owner.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    }).signers([vault, owner]).rpc();
+
+    const native_accounts = {
+      depositAccount: vault.publicKey,
+      pdaAuth: vault_auth,
+      solVault: vault_sol,
+      depositor: owner.publicKey,
+      beneficiary: owner.publicKey,
+      balance: owner_balance,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    };
+
+    const close = (refunds: anchor.web3.AccountMeta[]) => program.methods.closeDeposit().accounts({
+      depositAccount: vault.publicKey,
+      pdaAuth: vault_auth,
+      solVault: vault_sol,
+      depositAuth: owner.publicKey,
+      nftInventory: null,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      token2022Program: null,
+    }).remainingAccounts(refunds).signers([owner]).rpc();
+
+    await program.methods.depositNative(new anchor.BN(anchor.web3.LAMPORTS_PER_SOL), null).accounts(native_accounts).signers([owner]).rpc();
+
+    // a deposit by someone else cannot keep the vault open
+    await program.methods.depositNative(new anchor.BN(1), null).accounts({
+      ...native_accounts,
+      depositor: stranger.publicKey,
+      beneficiary: stranger.publicKey,
+      balance: stranger_balance,
+    }).signers([stranger]).rpc();
+
+    try {
+      await close([]);
+      assert.fail("vault holding a balance was closed");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "PositionsOpen");
+    }
+
+    await program.methods.withdrawNative(new anchor.BN(anchor.web3.LAMPORTS_PER_SOL)).accounts(native_accounts).signers([owner]).rpc();
+
+    // SOL sent to the vault directly is not owed to anyone and does not block closing
+    const donation = new anchor.web3.Transaction().add(
+      anchor.web3.SystemProgram.transfer({ fromPubkey: owner.publicKey, toPubkey: vault_sol, lamports: anchor.web3.LAMPORTS_PER_SOL }),
+    );
+    await anchor.web3.sendAndConfirmTransaction(provider.connection, donation, [owner]);
+
+    try {
+      await close([
+        { pubkey: stranger_balance, isSigner: false, isWritable: true },
+        { pubkey: owner.publicKey, isSigner: false, isWritable: true },
+      ]);
+      assert.fail("a depositor's balance was refunded to another wallet");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidRefund");
+    }
+
+    const stranger_before = await provider.connection.getBalance(stranger.publicKey);
+    const stranger_rent = await provider.connection.getBalance(stranger_balance);
+    await close([
+      { pubkey: owner_balance, isSigner: false, isWritable: true },
+      { pubkey: owner.publicKey, isSigner: false, isWritable: true },
+      { pubkey: stranger_balance, isSigner: false, isWritable: true },
+      { pubkey: stranger.publicKey, isSigner: false, isWritable: true },
+    ]);
+
+    assert.isNull(await provider.connection.getAccountInfo(vault.publicKey));
+    assert.isNull(await provider.connection.getAccountInfo(owner_balance));
+    assert.isNull(await provider.connection.getAccountInfo(stranger_balance));
+    assert.equal(await provider.connection.getBalance(stranger.publicKey), stranger_before + stranger_rent + 1);
+    assert.equal(await provider.connection.getBalance(vault_sol), 0);
+  });
+
+  xit("Locks the vault until a timestamp", async () => {
+    const unlock_ts = new anchor.BN(Math.floor(Date.now() / 1000) + 3600);
+
+    let set_lock_tx = await program.methods.setLock({ startTs: unlock_ts, cliffTs: unlock_ts, endTs: unlock_ts }).accounts({
+      depositAccount: deposit_account.publicKey,
+      depositAuth: deposit_auth.publicKey,
+    }).signers([deposit_auth]).rpc();
+
+    console.log(set_lock_tx);
+
+    let withdrawable = await program.methods.withdrawable().accounts({
+      depositAccount: deposit_account.publicKey,
+      balance: native_balance,
+    }).view();
+    assert.equal(withdrawable.toNumber(), 0);
+
+    try {
+      await program.methods.withdrawNative(new anchor.BN(1)).accounts({
+        depositAccount: deposit_account.publicKey,
+        pdaAuth: pda_auth,
+        solVault: sol_vault,
+        depositor: deposit_auth.publicKey,
+        balance: native_balance,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }).signers([deposit_auth]).rpc();
+      assert.fail("withdrawal of locked funds succeeded");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "FundsLocked");
+    }
+  });
+
+  xit("Leaves other depositors' balances unlocked", async () => {
+    const depositor = anchor.web3.Keypair.generate();
+
+    let res = await provider.connection.requestAirdrop(depositor.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+    let latestBlockHash = await provider.connection.getLatestBlockhash();
+    await provider.connection.confirmTransaction({
+      blockhash: latestBlockHash.blockhash,
+      lastValidBlockHeight: latestBlockHash.lastValidBlockHeight,
+      signature: res,
+    });
+
+    let [depositor_balance] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("balance"),
+      deposit_account.publicKey.toBuffer(),
+      depositor.publicKey.toBuffer()
+      ],
+      program.programId);
+
+    const accounts = {
+      depositAccount: deposit_account.publicKey,
+      pdaAuth: pda_auth,
+      solVault: sol_vault,
+      depositor: depositor.publicKey,
+      beneficiary: depositor.publicKey,
+      balance: depositor_balance,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    };
+
+    await program.methods.depositNative(new anchor.BN(anchor.web3.LAMPORTS_PER_SOL), null).accounts(accounts).signers([depositor]).rpc();
+    await program.methods.withdrawNative(new anchor.BN(anchor.web3.LAMPORTS_PER_SOL)).accounts(accounts).signers([depositor]).rpc();
+
+    let balance = await program.account.userBalance.fetch(depositor_balance);
+    assert.equal(balance.amount.toNumber(), 0);
+  });
+
+  xit("Locks a grant for its beneficiary", async () => {
+    const beneficiary = anchor.web3.Keypair.generate();
+    const unlock_ts = new anchor.BN(Math.floor(Date.now() / 1000) + 3600);
+    const lock = { startTs: unlock_ts, cliffTs: unlock_ts, endTs: unlock_ts };
+
+    let [grant_balance] = anchor.web3.PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("balance"),
+      deposit_account.publicKey.toBuffer(),
+      beneficiary.publicKey.toBuffer()
+      ],
+      program.programId);
+
+    const accounts = {
+      depositAccount: deposit_account.publicKey,
+      pdaAuth: pda_auth,
+      solVault: sol_vault,
+      depositor: deposit_auth.publicKey,
+      beneficiary: beneficiary.publicKey,
+      balance: grant_balance,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    };
+
+    await program.methods.depositNative(new anchor.BN(anchor.web3.LAMPORTS_PER_SOL), lock).accounts(accounts).signers([deposit_auth]).rpc();
+
+    let balance = await program.account.userBalance.fetch(grant_balance);
+    assert.ok(balance.owner.equals(beneficiary.publicKey));
+    assert.equal(balance.lock.endTs.toNumber(), unlock_ts.toNumber());
+
+    let withdrawable = await program.methods.withdrawable().accounts({
+      depositAccount: deposit_account.publicKey,
+      balance: grant_balance,
+    }).view();
+    assert.equal(withdrawable.toNumber(), 0);
+
+    try {
+      await program.methods.withdrawNative(new anchor.BN(1)).accounts({
+        ...accounts,
+        depositor: beneficiary.publicKey,
+      }).signers([beneficiary]).rpc();
+      assert.fail("withdrawal of a locked grant succeeded");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "FundsLocked");
+    }
+
+    // the lock cannot be added to a balance that already holds funds
+    try {
+      await program.methods.depositNative(new anchor.BN(1), lock).accounts(accounts).signers([deposit_auth]).rpc();
+      assert.fail("second lock on a grant was set");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidGrant");
+    }
+  });
+
+});;
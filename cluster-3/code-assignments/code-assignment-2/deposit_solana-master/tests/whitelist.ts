@@ -0,0 +1,89 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Whitelist } from "../target/types/whitelist";
+import { assert } from "chai";
+
+describe("whitelist", () => {
+
+  // Configure the client to use the local cluster.
+  let provider = anchor.AnchorProvider.local("http://127.0.0.1:8899")
+  const program = anchor.workspace.Whitelist as Program<Whitelist>;
+  const whitelist_account = anchor.web3.Keypair.generate();
+  const whitelist_auth = anchor.web3.Keypair.generate();
+  const member = anchor.web3.Keypair.generate();
+
+  let [entry] = anchor.web3.PublicKey.findProgramAddressSync(
+    [anchor.utils.bytes.utf8.encode("entry"),
+    whitelist_account.publicKey.toBuffer(),
+    member.publicKey.toBuffer()
+    ],
+    program.programId);
+
+  before(async () => {
+
+    let res = await provider.connection.requestAirdrop(whitelist_auth.publicKey, 10 * anchor.web3.LAMPORTS_PER_SOL);
+
+    let latestBlockHash = await provider.connection.getLatestBlockhash()
+
+    await provider.connection.confirmTransaction({
+      blockhash: latestBlockHash.blockhash,
+      lastValidBlockHeight: latestBlockHash.lastValidBlockHeight,
+      signature: res,
+    });
+
+  });
+
+  it("Is initialized!", async () => {
+    const tx = await program.methods.initialize()
+      .accounts({
+        whitelistAccount: whitelist_account.publicKey,
+        whitelistAuth: whitelist_auth.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }).signers([whitelist_account, whitelist_auth]).rpc();
+
+    console.log("Your transaction signature", tx);
+
+    let result = await program.account.whitelistBase.fetch(whitelist_account.publicKey);
+    assert.ok(result.whitelistAuth.equals(whitelist_auth.publicKey));
+  });
+
+  it("Adds an address to the whitelist", async () => {
+    await program.methods.addToWhitelist(member.publicKey)
+      .accounts({
+        whitelistAccount: whitelist_account.publicKey,
+        entry: entry,
+        whitelistAuth: whitelist_auth.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }).signers([whitelist_auth]).rpc();
+
+    await program.methods.isWhitelisted(member.publicKey)
+      .accounts({
+        whitelistAccount: whitelist_account.publicKey,
+        entry: entry,
+      }).rpc();
+
+    let result = await program.account.whitelistBase.fetch(whitelist_account.publicKey);
+    assert.equal(result.entryCount.toNumber(), 1);
+  });
+
+  it("Removes an address from the whitelist", async () => {
+    await program.methods.removeFromWhitelist(member.publicKey)
+      .accounts({
+        whitelistAccount: whitelist_account.publicKey,
+        entry: entry,
+        whitelistAuth: whitelist_auth.publicKey,
+      }).signers([whitelist_auth]).rpc();
+
+    try {
+      await program.methods.isWhitelisted(member.publicKey)
+        .accounts({
+          whitelistAccount: whitelist_account.publicKey,
+          entry: entry,
+        }).rpc();
+      assert.fail("removed address is still whitelisted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "NotWhitelisted");
+    }
+  });
+
+});
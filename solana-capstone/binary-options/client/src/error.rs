@@ -0,0 +1,76 @@
+use anchor_lang::error::ERROR_CODE_OFFSET;
+use binary_options::error::Errors;
+
+/// Every error the program can return, in declaration order.
+pub const ERRORS: &[Errors] = &[
+    Errors::CannotCreateBetting,
+    Errors::ExceededDescriptionMaxLength,
+    Errors::InvalidBetAmount,
+    Errors::InvalidStrikePrice,
+    Errors::InvalidTakerAmount,
+    Errors::InvalidPosition,
+    Errors::InvalidDisputeWindow,
+    Errors::InvalidExpiry,
+    Errors::InvalidDepositAmount,
+    Errors::InvalidFillAmount,
+    Errors::FillExceedsRemaining,
+    Errors::PredictionCannotBeSame,
+    Errors::PredictionDisAllowed,
+    Errors::InvalidParticipantsLimit,
+    Errors::OptionExpired,
+    Errors::OptionPartiallyFilled,
+    Errors::SelfReferral,
+    Errors::InvalidBetFees,
+    Errors::InvalidPriceFeed,
+    Errors::InvalidPriceAccount,
+    Errors::PythOffline,
+    Errors::TryToSerializePriceAccount,
+    Errors::OptionNotExpired,
+    Errors::InvalidSettlementMode,
+    Errors::OutsideObservationWindow,
+    Errors::ObservationTooSoon,
+    Errors::NotEnoughObservations,
+    Errors::PriceExponentChanged,
+    Errors::InvalidBettingState,
+    Errors::DisputeWindowOpen,
+    Errors::DisputeWindowClosed,
+    Errors::InvalidWithdrawAmount,
+    Errors::AmountNotEqualToTotalPayoutAmount,
+    Errors::AmountNotEqualToStake,
+    Errors::InvalidPrediction,
+    Errors::WithdrawalDisAllowed,
+    Errors::InvalidWinner,
+    Errors::AlreadyWithdrawn,
+    Errors::NothingToClaim,
+    Errors::RolloverUnavailable,
+    Errors::RolloverEnabled,
+    Errors::RolloverDisabled,
+    Errors::AlreadyRolledOver,
+    Errors::OrderBookFull,
+    Errors::OptionNotListed,
+    Errors::OrderBookMissing,
+    Errors::ListingAmountTooLow,
+    Errors::InvalidReferralFee,
+    Errors::ReferrerAccountMissing,
+    Errors::InvalidReferrer,
+    Errors::Unauthorized,
+    Errors::AccountNotInitialized,
+    Errors::AccountAlreadyInitialized,
+    Errors::AccountAlreadyMigrated,
+    Errors::Overflow,
+];
+
+/// Program error for a custom instruction error code, e.g. from `InstructionError::Custom`.
+pub fn from_code(code: u32) -> Option<Errors> {
+    ERRORS.iter().copied().find(|error| u32::from(*error) == code)
+}
+
+/// Message to show for a custom instruction error code of the program.
+pub fn message(code: u32) -> Option<String> {
+    from_code(code).map(|error| error.to_string())
+}
+
+/// Whether `code` falls in the range of the program's own errors rather than Anchor's.
+pub fn is_program_error(code: u32) -> bool {
+    code >= ERROR_CODE_OFFSET
+}
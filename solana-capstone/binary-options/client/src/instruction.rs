@@ -0,0 +1,419 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use binary_options::state::AdminConfig;
+use binary_options::{accounts, instruction, ParticipantPosition};
+
+use crate::pda::{self, AdminAddresses, OptionAddresses};
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: binary_options::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+fn referrer_account(referrer_auth: Option<Pubkey>) -> Option<Pubkey> {
+    referrer_auth.map(|referrer_auth| pda::referrer(&referrer_auth).0)
+}
+
+/// Creates the house accounts. `program` is the program id and must sign.
+pub fn initialize(
+    program: Pubkey,
+    config_account: Pubkey,
+    admin_deposit_account: Pubkey,
+    admin_auth: Pubkey,
+    config: AdminConfig,
+) -> Instruction {
+    let admin = AdminAddresses::new(admin_deposit_account);
+
+    build(
+        accounts::Initialize {
+            program,
+            config: config_account,
+            admin_deposit_account,
+            admin_pda_auth: admin.admin_pda_auth,
+            admin_sol_vault: admin.admin_sol_vault,
+            admin_auth,
+            system_program: system_program::ID,
+        },
+        instruction::Initialize { config },
+    )
+}
+
+/// Creates a binary option in the market of `config`; `deposit_account` is a new keypair that
+/// must sign. The option is listed in `order_book` when one is given.
+pub fn create_binary_options(
+    config: Pubkey,
+    deposit_account: Pubkey,
+    deposit_auth: Pubkey,
+    admin_deposit_account: Pubkey,
+    order_book: Option<Pubkey>,
+    args: instruction::CreateBinaryOptions,
+) -> Instruction {
+    let option = OptionAddresses::new(deposit_account);
+
+    build(
+        accounts::CreateBinaryOptions {
+            config,
+            deposit_account,
+            pda_auth: option.pda_auth,
+            sol_vault: option.sol_vault,
+            deposit_auth,
+            admin_deposit_account,
+            order_book,
+            referrer: referrer_account(args.referrer),
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// Takes the opposite side of an open binary option. `order_book` is the book the option is
+/// listed in, if any.
+pub fn accept_binary_options(
+    deposit_account: Pubkey,
+    deposit_auth: Pubkey,
+    admin_deposit_account: Pubkey,
+    order_book: Option<Pubkey>,
+    amount: u64,
+    participant_position: ParticipantPosition,
+    referrer: Option<Pubkey>,
+) -> Instruction {
+    let option = OptionAddresses::new(deposit_account);
+    let admin = AdminAddresses::new(admin_deposit_account);
+
+    build(
+        accounts::AcceptBinaryOptions {
+            admin_deposit_account,
+            admin_pda_auth: admin.admin_pda_auth,
+            admin_sol_vault: admin.admin_sol_vault,
+            deposit_account,
+            pda_auth: option.pda_auth,
+            sol_vault: option.sol_vault,
+            deposit_auth,
+            order_book,
+            referrer: referrer_account(referrer),
+            system_program: system_program::ID,
+        },
+        instruction::AcceptBinaryOptions {
+            amount,
+            participant_position,
+            referrer,
+        },
+    )
+}
+
+/// Fills part of the opposite side of an open option with `amount`.
+pub fn fill_binary_options(
+    deposit_account: Pubkey,
+    taker_auth: Pubkey,
+    order_book: Option<Pubkey>,
+    amount: u64,
+) -> Instruction {
+    let option = OptionAddresses::new(deposit_account);
+
+    build(
+        accounts::FillBinaryOptions {
+            deposit_account,
+            pda_auth: option.pda_auth,
+            sol_vault: option.sol_vault,
+            fill: pda::fill(&deposit_account, &taker_auth).0,
+            taker_auth,
+            order_book,
+            system_program: system_program::ID,
+        },
+        instruction::FillBinaryOptions { amount },
+    )
+}
+
+/// Withdraws a taker's share of the payout (or their fill after a refund) and closes their fill.
+pub fn claim_fill(deposit_account: Pubkey, taker_auth: Pubkey) -> Instruction {
+    let option = OptionAddresses::new(deposit_account);
+
+    build(
+        accounts::ClaimFill {
+            deposit_account,
+            pda_auth: option.pda_auth,
+            sol_vault: option.sol_vault,
+            fill: pda::fill(&deposit_account, &taker_auth).0,
+            taker_auth,
+            system_program: system_program::ID,
+        },
+        instruction::ClaimFill {},
+    )
+}
+
+/// Refunds the part of the maker's stake that was not filled by expiry.
+pub fn refund_unfilled(deposit_account: Pubkey, deposit_auth: Pubkey) -> Instruction {
+    let option = OptionAddresses::new(deposit_account);
+
+    build(
+        accounts::RefundUnfilled {
+            deposit_account,
+            pda_auth: option.pda_auth,
+            sol_vault: option.sol_vault,
+            deposit_auth,
+            system_program: system_program::ID,
+        },
+        instruction::RefundUnfilled {},
+    )
+}
+
+/// Cancels an option nobody has accepted yet, refunding the first participant.
+pub fn cancel_binary_options(deposit_account: Pubkey, deposit_auth: Pubkey, order_book: Option<Pubkey>) -> Instruction {
+    let option = OptionAddresses::new(deposit_account);
+
+    build(
+        accounts::CancelBinaryOptions {
+            deposit_account,
+            pda_auth: option.pda_auth,
+            sol_vault: option.sol_vault,
+            deposit_auth,
+            order_book,
+            system_program: system_program::ID,
+        },
+        instruction::CancelBinaryOptions {},
+    )
+}
+
+/// Withdraws the payout (or the participant's own stake after a refund).
+pub fn withdraw_participant_funds(deposit_account: Pubkey, deposit_auth: Pubkey, amount: u64) -> Instruction {
+    let option = OptionAddresses::new(deposit_account);
+
+    build(
+        accounts::WithdrawParticipantFunds {
+            deposit_account,
+            pda_auth: option.pda_auth,
+            sol_vault: option.sol_vault,
+            deposit_auth,
+            system_program: system_program::ID,
+        },
+        instruction::WithdrawParticipantFunds { amount },
+    )
+}
+
+/// Settles an option against the configured Pyth feed. Referrers are the `referrer_auth`
+/// keys recorded on the option, if any.
+pub fn process_prediction(
+    config: Pubkey,
+    pyth_price_feed_account: Pubkey,
+    deposit_account: Pubkey,
+    admin_deposit_account: Pubkey,
+    maker_referrer: Option<Pubkey>,
+    taker_referrer: Option<Pubkey>,
+) -> Instruction {
+    let option = OptionAddresses::new(deposit_account);
+    let admin = AdminAddresses::new(admin_deposit_account);
+
+    build(
+        accounts::ProcessPrediction {
+            config,
+            pyth_price_feed_account,
+            deposit_account,
+            pda_auth: option.pda_auth,
+            sol_vault: option.sol_vault,
+            admin_deposit_account,
+            admin_pda_auth: admin.admin_pda_auth,
+            admin_sol_vault: admin.admin_sol_vault,
+            maker_referrer: referrer_account(maker_referrer),
+            taker_referrer: referrer_account(taker_referrer),
+            system_program: system_program::ID,
+        },
+        instruction::ProcessPrediction {},
+    )
+}
+
+/// Releases fees and payout once the dispute window of a settlement has passed.
+pub fn finalize_settlement(
+    config: Pubkey,
+    deposit_account: Pubkey,
+    admin_deposit_account: Pubkey,
+    maker_referrer: Option<Pubkey>,
+    taker_referrer: Option<Pubkey>,
+) -> Instruction {
+    let option = OptionAddresses::new(deposit_account);
+    let admin = AdminAddresses::new(admin_deposit_account);
+
+    build(
+        accounts::FinalizeSettlement {
+            config,
+            deposit_account,
+            pda_auth: option.pda_auth,
+            sol_vault: option.sol_vault,
+            admin_deposit_account,
+            admin_pda_auth: admin.admin_pda_auth,
+            admin_sol_vault: admin.admin_sol_vault,
+            maker_referrer: referrer_account(maker_referrer),
+            taker_referrer: referrer_account(taker_referrer),
+            system_program: system_program::ID,
+        },
+        instruction::FinalizeSettlement {},
+    )
+}
+
+/// Creates the order book of the market configured by `config`.
+pub fn init_order_book(config: Pubkey, payer: Pubkey) -> Instruction {
+    build(
+        accounts::InitOrderBook {
+            config,
+            order_book: pda::order_book(&config).0,
+            payer,
+            system_program: system_program::ID,
+        },
+        instruction::InitOrderBook {},
+    )
+}
+
+/// Evicts expired offers from the order book of `config`, along with any of `options` that are no
+/// longer open.
+pub fn prune_order_book(config: Pubkey, options: &[Pubkey]) -> Instruction {
+    let mut prune = build(
+        accounts::PruneOrderBook {
+            order_book: pda::order_book(&config).0,
+        },
+        instruction::PruneOrderBook {},
+    );
+    prune
+        .accounts
+        .extend(options.iter().map(|option| AccountMeta::new_readonly(*option, false)));
+
+    prune
+}
+
+/// Opts a participant in or out of restaking their payout into the next round.
+pub fn set_rollover(deposit_account: Pubkey, participant: Pubkey, enabled: bool) -> Instruction {
+    build(
+        accounts::SetRollover {
+            deposit_account,
+            participant,
+        },
+        instruction::SetRollover { enabled },
+    )
+}
+
+/// Restakes the winner's payout of a finalized option into its next round; `payer` funds the new account.
+/// `order_book` is the book the option was listed in, if any, where the next round is listed too.
+pub fn rollover_round(deposit_account: Pubkey, order_book: Option<Pubkey>, payer: Pubkey) -> Instruction {
+    let option = OptionAddresses::new(deposit_account);
+    let next_round = OptionAddresses::new(pda::next_round(&deposit_account).0);
+
+    build(
+        accounts::RolloverRound {
+            deposit_account,
+            pda_auth: option.pda_auth,
+            sol_vault: option.sol_vault,
+            next_round: next_round.deposit_account,
+            next_pda_auth: next_round.pda_auth,
+            next_sol_vault: next_round.sol_vault,
+            order_book,
+            payer,
+            system_program: system_program::ID,
+        },
+        instruction::RolloverRound {},
+    )
+}
+
+/// Records the current price into the TWAP of an option settling in `SettlementMode::Twap`.
+pub fn observe_price(config: Pubkey, pyth_price_feed_account: Pubkey, deposit_account: Pubkey) -> Instruction {
+    build(
+        accounts::ObservePrice {
+            config,
+            pyth_price_feed_account,
+            deposit_account,
+        },
+        instruction::ObservePrice {},
+    )
+}
+
+/// Voids a settlement during its dispute window; signed by the admin or the resolver.
+pub fn void_settlement(
+    deposit_account: Pubkey,
+    admin_deposit_account: Pubkey,
+    resolver_auth: Pubkey,
+    refund: bool,
+) -> Instruction {
+    build(
+        accounts::VoidSettlement {
+            deposit_account,
+            admin_deposit_account,
+            resolver_auth,
+        },
+        instruction::VoidSettlement { refund },
+    )
+}
+
+/// Designates (or removes) the resolver allowed to void settlements.
+pub fn set_resolver(admin_deposit_account: Pubkey, admin_auth: Pubkey, resolver: Option<Pubkey>) -> Instruction {
+    build(
+        accounts::SetResolver {
+            admin_deposit_account,
+            admin_auth,
+        },
+        instruction::SetResolver { resolver },
+    )
+}
+
+/// Registers `referrer_auth` so it can be credited referral fees.
+pub fn register_referrer(referrer_auth: Pubkey) -> Instruction {
+    build(
+        accounts::RegisterReferrer {
+            referrer: pda::referrer(&referrer_auth).0,
+            referrer_auth,
+            system_program: system_program::ID,
+        },
+        instruction::RegisterReferrer {},
+    )
+}
+
+/// Claims every referral fee credited to `referrer_auth`.
+pub fn claim_referral_fees(referrer_auth: Pubkey) -> Instruction {
+    build(
+        accounts::ClaimReferralFees {
+            referrer: pda::referrer(&referrer_auth).0,
+            referrer_auth,
+        },
+        instruction::ClaimReferralFees {},
+    )
+}
+
+/// Grows a binary option created with an older layout to the current one; `payer` tops up rent.
+pub fn migrate_binary_option(deposit_account: Pubkey, payer: Pubkey) -> Instruction {
+    build(
+        accounts::MigrateBinaryOption {
+            deposit_account,
+            payer,
+            system_program: system_program::ID,
+        },
+        instruction::MigrateBinaryOption {},
+    )
+}
+
+/// Grows the admin deposit account created with an older layout to the current one.
+pub fn migrate_admin_deposit_account(admin_deposit_account: Pubkey, payer: Pubkey) -> Instruction {
+    build(
+        accounts::MigrateAdminDepositAccount {
+            admin_deposit_account,
+            payer,
+            system_program: system_program::ID,
+        },
+        instruction::MigrateAdminDepositAccount {},
+    )
+}
+
+/// Withdraws house fees to the admin.
+pub fn withdraw(admin_deposit_account: Pubkey, admin_auth: Pubkey, amount: u64) -> Instruction {
+    let admin = AdminAddresses::new(admin_deposit_account);
+
+    build(
+        accounts::Withdraw {
+            admin_deposit_account,
+            admin_pda_auth: admin.admin_pda_auth,
+            admin_sol_vault: admin.admin_sol_vault,
+            admin_auth,
+            system_program: system_program::ID,
+        },
+        instruction::Withdraw { amount },
+    )
+}
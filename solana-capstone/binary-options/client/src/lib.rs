@@ -0,0 +1,11 @@
+//! Client helpers for the binary-options program.
+//!
+//! `pda` derives every program address, `instruction` builds each instruction with the
+//! account metas the program expects, `state` decodes program accounts into plain structs and
+//! `error` maps the program's error codes back to their messages.
+pub mod error;
+pub mod instruction;
+pub mod pda;
+pub mod state;
+
+pub use binary_options::ID as PROGRAM_ID;
@@ -0,0 +1,86 @@
+use anchor_lang::prelude::Pubkey;
+
+/// Authority of a binary option's vault, seeded by the option account.
+pub fn auth(deposit_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"auth", deposit_account.as_ref()], &binary_options::ID)
+}
+
+/// Vault holding the stakes of a binary option, seeded by its `auth` address.
+pub fn sol_vault(pda_auth: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"sol_vault", pda_auth.as_ref()], &binary_options::ID)
+}
+
+/// Authority of the house vault, seeded by the admin deposit account.
+pub fn admin_auth(admin_deposit_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"admin_auth", admin_deposit_account.as_ref()], &binary_options::ID)
+}
+
+/// Vault collecting the house fees, seeded by its `admin_auth` address.
+pub fn admin_sol_vault(admin_pda_auth: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"admin_sol_vault", admin_pda_auth.as_ref()], &binary_options::ID)
+}
+
+/// Index of the open options of the market configured by `config`.
+pub fn order_book(config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"order_book", config.as_ref()], &binary_options::ID)
+}
+
+/// Option the payout of `deposit_account` is rolled over into.
+pub fn next_round(deposit_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"next_round", deposit_account.as_ref()], &binary_options::ID)
+}
+
+/// Record of the part of `deposit_account` filled by `taker_auth`.
+pub fn fill(deposit_account: &Pubkey, taker_auth: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"fill", deposit_account.as_ref(), taker_auth.as_ref()],
+        &binary_options::ID,
+    )
+}
+
+/// Account crediting referral fees to a referrer.
+pub fn referrer(referrer_auth: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"referrer", referrer_auth.as_ref()], &binary_options::ID)
+}
+
+/// Addresses owned by a single binary option.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OptionAddresses {
+    pub deposit_account: Pubkey,
+    pub pda_auth: Pubkey,
+    pub sol_vault: Pubkey,
+}
+
+impl OptionAddresses {
+    pub fn new(deposit_account: Pubkey) -> Self {
+        let (pda_auth, _) = auth(&deposit_account);
+        let (sol_vault, _) = sol_vault(&pda_auth);
+
+        OptionAddresses {
+            deposit_account,
+            pda_auth,
+            sol_vault,
+        }
+    }
+}
+
+/// Addresses of the house accounts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdminAddresses {
+    pub admin_deposit_account: Pubkey,
+    pub admin_pda_auth: Pubkey,
+    pub admin_sol_vault: Pubkey,
+}
+
+impl AdminAddresses {
+    pub fn new(admin_deposit_account: Pubkey) -> Self {
+        let (admin_pda_auth, _) = admin_auth(&admin_deposit_account);
+        let (admin_sol_vault, _) = admin_sol_vault(&admin_pda_auth);
+
+        AdminAddresses {
+            admin_deposit_account,
+            admin_pda_auth,
+            admin_sol_vault,
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator, Result};
+use binary_options::order_book::{OrderBook, OrderBookEntry, EXPIRY_BUCKET, POSITION_LONG};
+use binary_options::state::AdminConfig;
+use binary_options::{
+    BinaryOption, DepositBaseAdmin, Fill, ParticipantPosition, Referrer, SettlementMode, BETTING_STATE_ACCEPTED,
+    BETTING_STATE_FINALIZED, BETTING_STATE_OPEN, BETTING_STATE_REFUNDED, BETTING_STATE_SETTLED,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Position {
+    Long,
+    Short,
+}
+
+impl Position {
+    fn from_participant(position: ParticipantPosition) -> Option<Self> {
+        match position {
+            ParticipantPosition::Long => Some(Position::Long),
+            ParticipantPosition::Short => Some(Position::Short),
+            ParticipantPosition::Unknown => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BettingState {
+    /// Waiting for a second participant.
+    Open,
+    /// Both predictions made, waiting for settlement.
+    Accepted,
+    /// Settled, payout held during the dispute window.
+    Settled,
+    /// Payout can be withdrawn by the winner.
+    Finalized,
+    /// Settlement voided, each participant withdraws their own stake.
+    Refunded,
+    /// A state this client does not know about.
+    Unknown(u8),
+}
+
+impl From<u8> for BettingState {
+    fn from(state: u8) -> Self {
+        match state {
+            BETTING_STATE_OPEN => BettingState::Open,
+            BETTING_STATE_ACCEPTED => BettingState::Accepted,
+            BETTING_STATE_SETTLED => BettingState::Settled,
+            BETTING_STATE_FINALIZED => BettingState::Finalized,
+            BETTING_STATE_REFUNDED => BettingState::Refunded,
+            state => BettingState::Unknown(state),
+        }
+    }
+}
+
+/// A decoded `BinaryOption` account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BinaryOptionInfo {
+    pub maker: Pubkey,
+    pub maker_position: Option<Position>,
+    pub taker: Option<Pubkey>,
+    pub taker_position: Option<Position>,
+    pub winner: Option<Pubkey>,
+    pub description: String,
+    pub bet_amount: u64,
+    pub taker_amount: u64,
+    pub strike_price: u64,
+    /// Price the option was settled at, once a settlement stands.
+    pub settlement_price: Option<u64>,
+    pub total_payout: u64,
+    pub bet_fees: u64,
+    pub state: BettingState,
+    pub maker_referrer: Option<Pubkey>,
+    pub taker_referrer: Option<Pubkey>,
+    pub dispute_window: i64,
+    pub settled_at: Option<i64>,
+    pub maker_withdrawn: bool,
+    pub taker_withdrawn: bool,
+    pub settlement_mode: SettlementMode,
+    pub expiry: Option<i64>,
+    pub maker_rollover: bool,
+    pub taker_rollover: bool,
+    pub next_round: Option<Pubkey>,
+    pub order_book: Option<Pubkey>,
+    /// Taker stake received from partial fills, see `FillInfo`.
+    pub filled_amount: u64,
+    pub unfilled_refunded: bool,
+}
+
+impl BinaryOptionInfo {
+    /// Unix timestamp from which the payout can be withdrawn, once settled.
+    pub fn payout_claimable_at(&self) -> Option<i64> {
+        self.settled_at.map(|settled_at| settled_at + self.dispute_window)
+    }
+}
+
+impl From<BinaryOption> for BinaryOptionInfo {
+    fn from(option: BinaryOption) -> Self {
+        let state = BettingState::from(option.betting_state);
+        let settled = matches!(state, BettingState::Settled | BettingState::Finalized);
+
+        BinaryOptionInfo {
+            maker: option.deposit_auth,
+            maker_position: Position::from_participant(option.first_participant),
+            // options filled in parts have a taker per fill
+            taker: (option.made_prediction && option.filled_amount == 0).then_some(option.taker_auth),
+            taker_position: option
+                .made_prediction
+                .then(|| Position::from_participant(option.second_participant))
+                .flatten(),
+            winner: settled.then_some(option.winner_auth),
+            description: option.bet_description,
+            bet_amount: option.bet_amount,
+            taker_amount: option.taker_amount,
+            strike_price: option.strike_price,
+            settlement_price: settled.then_some(option.actual_price),
+            total_payout: option.total_payout,
+            bet_fees: option.bet_fees,
+            state,
+            maker_referrer: option.maker_referrer,
+            taker_referrer: option.taker_referrer,
+            dispute_window: option.dispute_window,
+            settled_at: settled.then_some(option.settled_at),
+            maker_withdrawn: option.maker_withdrawn,
+            taker_withdrawn: option.taker_withdrawn,
+            settlement_mode: option.settlement_mode,
+            expiry: (option.expiry > 0).then_some(option.expiry),
+            maker_rollover: option.maker_rollover,
+            taker_rollover: option.taker_rollover,
+            next_round: option.next_round,
+            order_book: option.order_book,
+            filled_amount: option.filled_amount,
+            unfilled_refunded: option.unfilled_refunded,
+        }
+    }
+}
+
+/// A decoded `DepositBaseAdmin` account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdminInfo {
+    pub admin: Pubkey,
+    pub resolver: Option<Pubkey>,
+    pub is_initialized: bool,
+}
+
+impl From<DepositBaseAdmin> for AdminInfo {
+    fn from(admin: DepositBaseAdmin) -> Self {
+        AdminInfo {
+            admin: admin.admin_auth,
+            resolver: admin.resolver,
+            is_initialized: admin.is_initialized,
+        }
+    }
+}
+
+/// A decoded `AdminConfig` account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfigInfo {
+    pub price_feed: Pubkey,
+    pub fee_bps: u16,
+    pub referral_fee_bps: u16,
+    pub dispute_window: i64,
+    pub settlement_mode: SettlementMode,
+}
+
+impl From<AdminConfig> for ConfigInfo {
+    fn from(config: AdminConfig) -> Self {
+        ConfigInfo {
+            price_feed: config.price_feed_id,
+            fee_bps: config.fee_bps,
+            referral_fee_bps: config.referral_fee_bps,
+            dispute_window: config.dispute_window,
+            settlement_mode: config.settlement_mode,
+        }
+    }
+}
+
+/// A decoded `Referrer` account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReferrerInfo {
+    pub referrer: Pubkey,
+    pub accrued_fees: u64,
+    pub claimed_fees: u64,
+}
+
+impl ReferrerInfo {
+    pub fn unclaimed_fees(&self) -> u64 {
+        self.accrued_fees.saturating_sub(self.claimed_fees)
+    }
+}
+
+impl From<Referrer> for ReferrerInfo {
+    fn from(referrer: Referrer) -> Self {
+        ReferrerInfo {
+            referrer: referrer.referrer_auth,
+            accrued_fees: referrer.accrued_fees,
+            claimed_fees: referrer.claimed_fees,
+        }
+    }
+}
+
+/// A decoded `Fill` account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FillInfo {
+    pub option: Pubkey,
+    pub taker: Pubkey,
+    pub amount: u64,
+}
+
+impl From<Fill> for FillInfo {
+    fn from(fill: Fill) -> Self {
+        FillInfo {
+            option: fill.deposit_account,
+            taker: fill.taker_auth,
+            amount: fill.amount,
+        }
+    }
+}
+
+/// An open option listed in an order book.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OfferInfo {
+    pub option: Pubkey,
+    pub strike_price: u64,
+    pub expiry: Option<i64>,
+    pub bet_amount: u64,
+    pub taker_amount: u64,
+    pub maker_position: Position,
+}
+
+impl From<&OrderBookEntry> for OfferInfo {
+    fn from(entry: &OrderBookEntry) -> Self {
+        OfferInfo {
+            option: entry.option,
+            strike_price: entry.strike_price,
+            expiry: (entry.expiry > 0).then_some(entry.expiry),
+            bet_amount: entry.bet_amount,
+            taker_amount: entry.taker_amount,
+            maker_position: if entry.maker_position == POSITION_LONG {
+                Position::Long
+            } else {
+                Position::Short
+            },
+        }
+    }
+}
+
+/// A decoded `OrderBook` account. Offers are sorted by strike, expiry hour and then price,
+/// best price first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderBookInfo {
+    pub config: Pubkey,
+    pub offers: Vec<OfferInfo>,
+}
+
+impl OrderBookInfo {
+    /// Best priced offer a taker taking `taker_position` can fill for a strike and an expiry
+    /// (matched to the hour, `None` for options without one).
+    pub fn best_offer(&self, strike_price: u64, expiry: Option<i64>, taker_position: Position) -> Option<&OfferInfo> {
+        let bucket = expiry.unwrap_or(0) / EXPIRY_BUCKET;
+        self.offers.iter().find(|offer| {
+            offer.strike_price == strike_price &&
+                offer.expiry.unwrap_or(0) / EXPIRY_BUCKET == bucket &&
+                offer.maker_position != taker_position
+        })
+    }
+}
+
+// checks the account discriminator before decoding
+fn decode<T: AccountDeserialize>(mut data: &[u8]) -> Result<T> {
+    T::try_deserialize(&mut data)
+}
+
+pub fn decode_binary_option(data: &[u8]) -> Result<BinaryOptionInfo> {
+    decode::<BinaryOption>(data).map(BinaryOptionInfo::from)
+}
+
+pub fn decode_admin(data: &[u8]) -> Result<AdminInfo> {
+    decode::<DepositBaseAdmin>(data).map(AdminInfo::from)
+}
+
+pub fn decode_config(data: &[u8]) -> Result<ConfigInfo> {
+    decode::<AdminConfig>(data).map(ConfigInfo::from)
+}
+
+pub fn decode_referrer(data: &[u8]) -> Result<ReferrerInfo> {
+    decode::<Referrer>(data).map(ReferrerInfo::from)
+}
+
+pub fn decode_fill(data: &[u8]) -> Result<FillInfo> {
+    decode::<Fill>(data).map(FillInfo::from)
+}
+
+// zero-copy account, read without requiring the data to be aligned
+pub fn decode_order_book(data: &[u8]) -> Result<OrderBookInfo> {
+    let len = 8 + std::mem::size_of::<OrderBook>();
+    if data.len() < len {
+        return Err(ErrorCode::AccountDidNotDeserialize.into());
+    }
+    if data[..8] != OrderBook::discriminator() {
+        return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+    }
+
+    let order_book: OrderBook = bytemuck::pod_read_unaligned(&data[8..len]);
+    Ok(OrderBookInfo {
+        config: order_book.config,
+        offers: order_book.entries().iter().map(OfferInfo::from).collect(),
+    })
+}
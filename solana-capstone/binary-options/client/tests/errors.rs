@@ -0,0 +1,94 @@
+// Error codes are part of the program's interface: clients map them to messages, so a variant must
+// keep its code once released.
+use binary_options::error::Errors;
+use binary_options_client::error;
+
+#[test]
+fn error_codes_are_stable() {
+    let codes = [
+        (Errors::InvalidWithdrawAmount, 6000),
+        (Errors::AmountNotEqualToTotalPayoutAmount, 6003),
+        (Errors::InvalidDepositAmount, 6004),
+        (Errors::InvalidPrediction, 6005),
+        (Errors::CannotCreateBetting, 6007),
+        (Errors::ExceededDescriptionMaxLength, 6008),
+        (Errors::PredictionCannotBeSame, 6009),
+        (Errors::PredictionDisAllowed, 6010),
+        (Errors::WithdrawalDisAllowed, 6011),
+        (Errors::InvalidWinner, 6012),
+        (Errors::InvalidParticipantsLimit, 6013),
+        (Errors::AccountNotInitialized, 6014),
+        (Errors::AccountAlreadyInitialized, 6015),
+        (Errors::Unauthorized, 6016),
+        (Errors::Overflow, 6020),
+        (Errors::InvalidPriceAccount, 6021),
+        (Errors::PythOffline, 6022),
+        (Errors::TryToSerializePriceAccount, 6024),
+        (Errors::InvalidReferralFee, 6025),
+        (Errors::SelfReferral, 6026),
+        (Errors::ReferrerAccountMissing, 6027),
+        (Errors::InvalidReferrer, 6028),
+        (Errors::NothingToClaim, 6029),
+        (Errors::InvalidDisputeWindow, 6030),
+        (Errors::DisputeWindowOpen, 6031),
+        (Errors::DisputeWindowClosed, 6032),
+        (Errors::InvalidBettingState, 6033),
+        (Errors::AlreadyWithdrawn, 6034),
+        (Errors::AmountNotEqualToStake, 6035),
+        (Errors::AccountAlreadyMigrated, 6036),
+        (Errors::InvalidExpiry, 6037),
+        (Errors::OptionExpired, 6038),
+        (Errors::OptionNotExpired, 6039),
+        (Errors::InvalidSettlementMode, 6040),
+        (Errors::OutsideObservationWindow, 6041),
+        (Errors::ObservationTooSoon, 6042),
+        (Errors::NotEnoughObservations, 6043),
+        (Errors::RolloverUnavailable, 6044),
+        (Errors::RolloverEnabled, 6045),
+        (Errors::RolloverDisabled, 6046),
+        (Errors::AlreadyRolledOver, 6047),
+        (Errors::OrderBookFull, 6048),
+        (Errors::OptionNotListed, 6049),
+        (Errors::OrderBookMissing, 6050),
+        (Errors::ListingAmountTooLow, 6061),
+        (Errors::OptionPartiallyFilled, 6051),
+        (Errors::FillExceedsRemaining, 6052),
+        (Errors::InvalidBetAmount, 6053),
+        (Errors::InvalidStrikePrice, 6054),
+        (Errors::InvalidTakerAmount, 6055),
+        (Errors::InvalidPosition, 6056),
+        (Errors::InvalidFillAmount, 6057),
+        (Errors::InvalidBetFees, 6058),
+        (Errors::InvalidPriceFeed, 6059),
+        (Errors::PriceExponentChanged, 6060),
+    ];
+
+    for (error, code) in codes {
+        assert_eq!(u32::from(error), code, "{:?} changed its code", error);
+    }
+}
+
+#[test]
+fn error_codes_are_unique() {
+    let mut codes: Vec<u32> = error::ERRORS
+        .iter()
+        .map(|error| u32::from(*error))
+        .collect();
+    let len = codes.len();
+    codes.sort_unstable();
+    codes.dedup();
+    assert_eq!(codes.len(), len);
+}
+
+#[test]
+fn codes_map_back_to_messages() {
+    for program_error in error::ERRORS.iter().copied() {
+        let code = u32::from(program_error);
+        assert!(error::is_program_error(code));
+        assert_eq!(error::message(code), Some(program_error.to_string()));
+    }
+
+    // retired codes no longer map to an error
+    assert!(error::from_code(6001).is_none());
+    assert!(error::message(6023).is_none());
+}
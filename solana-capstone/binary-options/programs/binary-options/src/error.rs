@@ -1,56 +1,138 @@
 use anchor_lang::prelude::*;
 
+// Program errors. The on-chain code of a variant is 6000 + its discriminant, and clients map these
+// codes to messages, so discriminants are fixed: never renumber a variant or reuse a retired number,
+// new variants take the next free one.
+//
+// Retired: 1 (AmountNotgreaterThanZero), 2 (ExceededTotalPayoutAmount), 6 (InvalidWinningAmount),
+// 17 (ReInitialize), 18 (UnInitialize), 19 (InvalidArgument), 23 (LoanValueTooHigh).
 #[error_code]
 pub enum Errors {
-    #[msg("Insufficient amount to withdraw.")]
-    InvalidWithdrawAmount,
-    #[msg("Amount must be greater than zero.")]
-    AmountNotgreaterThanZero,
-    #[msg("Withdrawal amount exceeds total payout amount.")]
-    ExceededTotalPayoutAmount,
-    #[msg("Withdrawal amount must be equal to total payout amount.")]
-    AmountNotEqualToTotalPayoutAmount,
-    #[msg("Deposit amount must be equal to bet_amount.")]
-    InvalidDepositAmount,
-    #[msg("Participant must make a prediction and win it.")]
-    InvalidPrediction,
-    #[msg("Winning Amount exceeds deposited amount.")]
-    InvalidWinningAmount,
-    #[msg("Betting cannot be created, missing data")]
-    CannotCreateBetting,
-    #[msg("Exceeded betting description max length")]
-    ExceededDescriptionMaxLength,
-    #[msg("Both predictions cannot not be same.")]
-    PredictionCannotBeSame,
+    // option parameters
+    #[msg("Bet description cannot be empty.")]
+    CannotCreateBetting = 7,
+    #[msg("Bet description exceeds 40 bytes.")]
+    ExceededDescriptionMaxLength = 8,
+    #[msg("Bet amount must be greater than zero.")]
+    InvalidBetAmount = 53,
+    #[msg("Strike price must be greater than zero.")]
+    InvalidStrikePrice = 54,
+    #[msg("Taker amount must be greater than zero.")]
+    InvalidTakerAmount = 55,
+    #[msg("Position must be long or short.")]
+    InvalidPosition = 56,
+    #[msg("Dispute window must be between zero and seven days.")]
+    InvalidDisputeWindow = 30,
+    #[msg("Expiry must be in the future, and is required to settle on a TWAP.")]
+    InvalidExpiry = 37,
+
+    // taking an option
+    #[msg("Deposit amount must be equal to the taker amount.")]
+    InvalidDepositAmount = 4,
+    #[msg("Fill amount must be greater than zero.")]
+    InvalidFillAmount = 57,
+    #[msg("Fill exceeds the amount left to fill.")]
+    FillExceedsRemaining = 52,
+    #[msg("Both predictions cannot be the same.")]
+    PredictionCannotBeSame = 9,
     #[msg("Single participant is not allowed to take both predictions.")]
-    PredictionDisAllowed,
+    PredictionDisAllowed = 10,
+    #[msg("Option is not open or has already been taken.")]
+    InvalidParticipantsLimit = 13,
+    #[msg("Option has expired.")]
+    OptionExpired = 38,
+    #[msg("Option is being filled in parts.")]
+    OptionPartiallyFilled = 51,
+    #[msg("Participant cannot refer themselves.")]
+    SelfReferral = 26,
+
+    // settlement
+    #[msg("Protocol fee must be greater than zero and at most 10% of the pot.")]
+    InvalidBetFees = 58,
+    #[msg("Price feed account does not match the configured feed.")]
+    InvalidPriceFeed = 59,
+    #[msg("Pyth price account could not be read.")]
+    InvalidPriceAccount = 21,
+    #[msg("Pyth price is older than the staleness threshold.")]
+    PythOffline = 22,
+    #[msg("Program should not try to serialize a price account.")]
+    TryToSerializePriceAccount = 24,
+    #[msg("Option has not expired yet.")]
+    OptionNotExpired = 39,
+    #[msg("Option does not settle on a TWAP.")]
+    InvalidSettlementMode = 40,
+    #[msg("Price can only be observed in the window before expiry.")]
+    OutsideObservationWindow = 41,
+    #[msg("Price was observed too recently.")]
+    ObservationTooSoon = 42,
+    #[msg("Not enough price observations to settle.")]
+    NotEnoughObservations = 43,
+    #[msg("Price exponent changed between observations.")]
+    PriceExponentChanged = 60,
+    #[msg("Binary option is not in the required betting state.")]
+    InvalidBettingState = 33,
+
+    // disputes
+    #[msg("Settlement is still within its dispute window.")]
+    DisputeWindowOpen = 31,
+    #[msg("Dispute window has already closed.")]
+    DisputeWindowClosed = 32,
+
+    // withdrawals
+    #[msg("Withdrawal amount must be greater than zero.")]
+    InvalidWithdrawAmount = 0,
+    #[msg("Withdrawal amount must be equal to total payout amount.")]
+    AmountNotEqualToTotalPayoutAmount = 3,
+    #[msg("Refund amount must be equal to the participant's stake.")]
+    AmountNotEqualToStake = 35,
+    #[msg("Participant must have taken part in the prediction.")]
+    InvalidPrediction = 5,
     #[msg("Participant not allowed to make a withdrawal.")]
-    WithdrawalDisAllowed,
-    #[msg("Invalid participant winner.")]
-    InvalidWinner,
-    #[msg("Create options not initialised or participants limit of two cannot be exceeded.")]
-    InvalidParticipantsLimit,
+    WithdrawalDisAllowed = 11,
+    #[msg("Only the winner can withdraw the payout.")]
+    InvalidWinner = 12,
+    #[msg("Participant has already withdrawn their funds.")]
+    AlreadyWithdrawn = 34,
+    #[msg("There is nothing to claim.")]
+    NothingToClaim = 29,
+
+    // rollover
+    #[msg("Only options with an expiry can roll over.")]
+    RolloverUnavailable = 44,
+    #[msg("Payout is rolled over into the next round, disable rollover to withdraw.")]
+    RolloverEnabled = 45,
+    #[msg("Winner has not enabled rollover.")]
+    RolloverDisabled = 46,
+    #[msg("Option was already rolled over.")]
+    AlreadyRolledOver = 47,
+
+    // order book
+    #[msg("Order book is full.")]
+    OrderBookFull = 48,
+    #[msg("Option is not listed in the order book.")]
+    OptionNotListed = 49,
+    #[msg("Order book the option is listed in must be provided.")]
+    OrderBookMissing = 50,
+    #[msg("Bet amount is below the minimum for listing in the order book.")]
+    ListingAmountTooLow = 61,
+
+    // referrals
+    #[msg("Referral fee cannot exceed 10000 basis points.")]
+    InvalidReferralFee = 25,
+    #[msg("Referrer account must be provided for a referred participant.")]
+    ReferrerAccountMissing = 27,
+    #[msg("Referrer account does not match the participant's referrer.")]
+    InvalidReferrer = 28,
+
+    // admin and accounts
+    #[msg("You are not authorized to perform this action.")]
+    Unauthorized = 16,
     #[msg("Account is not initialized.")]
-    AccountNotInitialized,
+    AccountNotInitialized = 14,
     #[msg("Account is already initialized.")]
-    AccountAlreadyInitialized,
-    //
-    #[msg("You are not authorized to perform this action.")]
-    Unauthorized,
-    #[msg("The config has already been initialized.")]
-    ReInitialize,
-    #[msg("The config has not been initialized.")]
-    UnInitialize,
-    #[msg("Argument is invalid.")]
-    InvalidArgument,
+    AccountAlreadyInitialized = 15,
+    #[msg("Account already uses the current layout.")]
+    AccountAlreadyMigrated = 36,
     #[msg("An overflow occurs.")]
-    Overflow,
-    #[msg("Pyth has an internal error.")]
-    PythError,
-    #[msg("Pyth price oracle is offline.")]
-    PythOffline,
-    #[msg("The loan value is higher than the collateral value.")]
-    LoanValueTooHigh,
-    #[msg("Program should not try to serialize a price account.")]
-    TryToSerializePriceAccount,
-}
\ No newline at end of file
+    Overflow = 20,
+}
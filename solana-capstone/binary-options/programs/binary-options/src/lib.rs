@@ -1,6 +1,4 @@
-use anchor_lang::{prelude::*, system_program};
-//
-use std::mem::size_of;
+use anchor_lang::{prelude::*, system_program, Discriminator};
 //use anchor_lang::prelude::*;
 //use solana_program::account_info::AccountInfo;
 
@@ -8,13 +6,29 @@ pub mod state;
 use state::PriceFeed;
 use state::AdminConfig;
 
-mod error;
+pub mod error;
 use error::Errors;
 
+pub mod order_book;
+use order_book::{OrderBook, OrderBookEntry, MIN_LISTING_AMOUNT, POSITION_LONG, POSITION_SHORT};
+
 declare_id!("JCqZPL84bJQKfQ1FZ4cYSWddYNpQCBXPjowjkCcdn9ZB");
 
 const DESCRIPTION_LENGTH: usize = 40; // betting description length
 const STALENESS_THRESHOLD : u64 = 60; // staleness threshold in seconds 60
+const MAX_BPS: u64 = 10_000; // 100% expressed in basis points
+const MAX_FEE_BPS: u64 = 1_000; // the protocol fee can never take more than 10% of the pot
+const MAX_DISPUTE_WINDOW: i64 = 7 * 24 * 60 * 60; // longest dispute window in seconds (7 days)
+const TWAP_WINDOW: i64 = 5 * 60; // observations are accumulated over the final 5 minutes before expiry
+const MIN_OBSERVATION_INTERVAL: i64 = 10; // minimum spacing between two TWAP observations in seconds
+const MIN_TWAP_DURATION: i64 = 60; // a TWAP must cover at least this many seconds to settle
+
+// betting states of a binary option
+pub const BETTING_STATE_OPEN: u8 = 1; // created, waiting for a second participant
+pub const BETTING_STATE_ACCEPTED: u8 = 2; // limit of two participants has been met
+pub const BETTING_STATE_SETTLED: u8 = 3; // prediction processed, payout held during the dispute window
+pub const BETTING_STATE_FINALIZED: u8 = 4; // payout can be withdrawn by the winner
+pub const BETTING_STATE_REFUNDED: u8 = 5; // settlement voided, participants withdraw their own stakes
 
 #[program]
 pub mod binary_options {
@@ -24,6 +38,21 @@ pub mod binary_options {
         let deposit_account = &mut ctx.accounts.admin_deposit_account;
         let config_account = &mut ctx.accounts.config;
 
+        // the fee is fixed per market so a settler cannot choose how much of the pot is taken
+        if config.fee_bps == 0 || config.fee_bps as u64 > MAX_FEE_BPS {
+            return Err(Errors::InvalidBetFees.into());
+        }
+
+        // dispute_window of zero means the payout is claimable as soon as the prediction is processed
+        if config.dispute_window < 0 || config.dispute_window > MAX_DISPUTE_WINDOW {
+            return Err(Errors::InvalidDisputeWindow.into());
+        }
+
+        // referral share is a portion of the protocol fee so it can never exceed 100%
+        if config.referral_fee_bps as u64 > MAX_BPS {
+            return Err(Errors::InvalidReferralFee.into());
+        }
+
         config_account.set_inner(config);
 
         deposit_account.admin_auth = *ctx.accounts.admin_auth.key;
@@ -34,7 +63,7 @@ pub mod binary_options {
         Ok(())
     }
 
-    pub fn create_binary_options(ctx: Context<CreateBinaryOptions>, bet_description: String, bet_amount: u64, strike_price: u64, taker_amount: u64, participantPosition: ParticipantPosition) -> Result<()> {
+    pub fn create_binary_options(ctx: Context<CreateBinaryOptions>, bet_description: String, bet_amount: u64, strike_price: u64, taker_amount: u64, participant_position: ParticipantPosition, referrer: Option<Pubkey>, expiry: i64) -> Result<()> {
         if bet_description.trim().is_empty() {
             return Err(Errors::CannotCreateBetting.into());
         }
@@ -42,6 +71,11 @@ pub mod binary_options {
             return Err(Errors::ExceededDescriptionMaxLength.into());
         }
 
+        // the maker must pick a side
+        if let ParticipantPosition::Unknown = participant_position {
+            return Err(Errors::InvalidPosition.into());
+        }
+
         // bet_amount
         let valid_amount = {
             if bet_amount > 0 {
@@ -51,7 +85,7 @@ pub mod binary_options {
         };
         // amount must be greater than zero
         if !valid_amount {
-            return Err(Errors::AmountNotgreaterThanZero.into());
+            return Err(Errors::InvalidBetAmount.into());
         }
         
         // strike_price
@@ -61,9 +95,9 @@ pub mod binary_options {
             }
             else{false}
         };
-        // amount must be greater than zero
+        // strike price must be greater than zero
         if !valid_amount {
-            return Err(Errors::AmountNotgreaterThanZero.into());
+            return Err(Errors::InvalidStrikePrice.into());
         }
 
         // taker_amount
@@ -75,9 +109,25 @@ pub mod binary_options {
         };
         // amount must be greater than zero
         if !valid_amount {
-            return Err(Errors::AmountNotgreaterThanZero.into());
+            return Err(Errors::InvalidTakerAmount.into());
         }
         
+        // participants cannot refer themselves to collect part of their own fees
+        if referrer == Some(*ctx.accounts.deposit_auth.key) {
+            return Err(Errors::SelfReferral.into());
+        }
+        check_referrer(referrer, &ctx.accounts.referrer)?;
+
+        // expiry of zero means the option can be settled as soon as it is accepted. A TWAP is
+        // accumulated over the window before expiry, so markets settling on one need it.
+        let settlement_mode = ctx.accounts.config.settlement_mode;
+        if expiry < 0 || (expiry > 0 && expiry <= Clock::get()?.unix_timestamp) {
+            return Err(Errors::InvalidExpiry.into());
+        }
+        if settlement_mode == SettlementMode::Twap && expiry == 0 {
+            return Err(Errors::InvalidExpiry.into());
+        }
+
         let deposit_account = &mut ctx.accounts.deposit_account;
         let deposit_auth = &ctx.accounts.deposit_auth;
         let sys_program = &ctx.accounts.system_program;
@@ -90,8 +140,36 @@ pub mod binary_options {
         deposit_account.bet_amount = bet_amount;
         deposit_account.strike_price = strike_price;
         deposit_account.taker_amount = taker_amount;
-        deposit_account.first_participant = participantPosition;
-        deposit_account.betting_state = 1;
+        deposit_account.first_participant = participant_position;
+        deposit_account.betting_state = BETTING_STATE_OPEN;
+        deposit_account.maker_referrer = referrer;
+        deposit_account.settlement_mode = settlement_mode;
+        deposit_account.expiry = expiry;
+        if expiry > 0 {
+            deposit_account.round_duration = expiry - Clock::get()?.unix_timestamp;
+        }
+
+        // list the option so takers can find it by strike and expiry
+        if let Some(order_book) = &ctx.accounts.order_book {
+            if bet_amount < MIN_LISTING_AMOUNT {
+                return Err(Errors::ListingAmountTooLow.into());
+            }
+            let maker_position = match participant_position {
+                ParticipantPosition::Long => POSITION_LONG,
+                ParticipantPosition::Short => POSITION_SHORT,
+                ParticipantPosition::Unknown => return Err(Errors::InvalidPosition.into()),
+            };
+            order_book.load_mut()?.insert(OrderBookEntry {
+                option: deposit_account.key(),
+                strike_price,
+                expiry,
+                bet_amount,
+                taker_amount,
+                maker_position,
+                ..OrderBookEntry::default()
+            })?;
+            deposit_account.order_book = Some(order_book.key());
+        }
 
         let cpi_accounts = system_program::Transfer {
             from: deposit_auth.to_account_info(),
@@ -106,7 +184,7 @@ pub mod binary_options {
     }
 
     //  accept binary options and deposit native sol
-    pub fn accept_binary_options(ctx: Context<AcceptBinaryOptions>, amount: u64, participant_position: ParticipantPosition) -> Result<()> {
+    pub fn accept_binary_options(ctx: Context<AcceptBinaryOptions>, amount: u64, participant_position: ParticipantPosition, referrer: Option<Pubkey>) -> Result<()> {
         let valid_amount = {
             if amount > 0 {
                 true
@@ -115,7 +193,7 @@ pub mod binary_options {
         };
         // amount must be greater than zero
         if !valid_amount {
-            return Err(Errors::AmountNotgreaterThanZero.into());
+            return Err(Errors::InvalidDepositAmount.into());
         }
         
         let deposit_account = &mut ctx.accounts.deposit_account;
@@ -133,11 +211,27 @@ pub mod binary_options {
             return Err(Errors::InvalidDepositAmount.into());
         }
 
+        // an expired option can no longer be taken
+        if deposit_account.expiry > 0 && Clock::get()?.unix_timestamp >= deposit_account.expiry {
+            return Err(Errors::OptionExpired.into());
+        }
+
+        // once takers fill it in parts the rest can only be filled in parts too
+        if deposit_account.filled_amount > 0 {
+            return Err(Errors::OptionPartiallyFilled.into());
+        }
+
         // first participant is not allowed to make prediction since they had previously done so in create options.
         if deposit_account.deposit_auth.eq(deposit_auth.key) {
             return Err(Errors::PredictionDisAllowed.into());
         }
 
+        // participants cannot refer themselves to collect part of their own fees
+        if referrer == Some(*deposit_auth.key) {
+            return Err(Errors::SelfReferral.into());
+        }
+        check_referrer(referrer, &ctx.accounts.referrer)?;
+
         let first_participant_position = {
             match deposit_account.first_participant {
                 ParticipantPosition::Long => true,
@@ -174,8 +268,11 @@ pub mod binary_options {
 
         // Lets maintain the pubkey of the second participant
         deposit_account.taker_auth = *ctx.accounts.deposit_auth.key;
+        deposit_account.taker_referrer = referrer;
         // Lets change the betting state to indicate limit of two participants has been met
-        deposit_account.betting_state = 2;
+        deposit_account.betting_state = BETTING_STATE_ACCEPTED;
+
+        unlist(deposit_account, &ctx.accounts.order_book)?;
 
         // step 1: deposit sol to participants(limited to two) vault
         let cpi_accounts = system_program::Transfer {
@@ -190,6 +287,199 @@ pub mod binary_options {
         Ok(())
     }
 
+    // takes part of the opposite side of an open option. Each taker fills once and is recorded in a
+    // Fill account; the option is accepted once it is completely filled, or settles at expiry with
+    // the part of the maker's stake that was matched.
+    pub fn fill_binary_options(ctx: Context<FillBinaryOptions>, amount: u64) -> Result<()> {
+        let valid_amount = {
+            if amount > 0 {
+                true
+            }
+            else{false}
+        };
+        // amount must be greater than zero
+        if !valid_amount {
+            return Err(Errors::InvalidFillAmount.into());
+        }
+
+        let deposit_account = &mut ctx.accounts.deposit_account;
+        let fill = &mut ctx.accounts.fill;
+        let taker_auth = &ctx.accounts.taker_auth;
+        let sys_program = &ctx.accounts.system_program;
+
+        // the unfilled stake is refunded at expiry, so only options with one can be filled in parts
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        if deposit_account.expiry == 0 {
+            return Err(Errors::InvalidExpiry.into());
+        }
+        if current_timestamp >= deposit_account.expiry {
+            return Err(Errors::OptionExpired.into());
+        }
+
+        // first participant is not allowed to take the other side of their own option
+        if deposit_account.deposit_auth.eq(taker_auth.key) {
+            return Err(Errors::PredictionDisAllowed.into());
+        }
+
+        let filled_amount = deposit_account.filled_amount.checked_add(amount).ok_or(Errors::Overflow)?;
+        if filled_amount > deposit_account.taker_amount {
+            return Err(Errors::FillExceedsRemaining.into());
+        }
+
+        fill.deposit_account = deposit_account.key();
+        fill.taker_auth = *taker_auth.key;
+        fill.amount = amount;
+        fill.bump = *ctx.bumps.get("fill").unwrap();
+
+        deposit_account.filled_amount = filled_amount;
+        deposit_account.made_prediction = true;
+        deposit_account.second_participant = match deposit_account.first_participant {
+            ParticipantPosition::Long => ParticipantPosition::Short,
+            _ => ParticipantPosition::Long,
+        };
+
+        if filled_amount == deposit_account.taker_amount {
+            deposit_account.betting_state = BETTING_STATE_ACCEPTED;
+            unlist(deposit_account, &ctx.accounts.order_book)?;
+        }
+        else {
+            // the listing shows what is left to fill
+            let (matched_stake, _) = deposit_account.matched_stakes();
+            relist(
+                deposit_account,
+                &ctx.accounts.order_book,
+                deposit_account.bet_amount - matched_stake,
+                deposit_account.taker_amount - filled_amount,
+            )?;
+        }
+
+        let cpi_accounts = system_program::Transfer {
+            from: taker_auth.to_account_info(),
+            to: ctx.accounts.sol_vault.to_account_info(),
+        };
+
+        let cpi = CpiContext::new(sys_program.to_account_info(), cpi_accounts);
+
+        system_program::transfer(cpi, amount)?;
+
+        Ok(())
+    }
+
+    // taker withdraws their share of the payout (or their fill after a refund) and closes their fill record
+    pub fn claim_fill(ctx: Context<ClaimFill>) -> Result<()> {
+        let deposit_account = &ctx.accounts.deposit_account;
+        let fill = &ctx.accounts.fill;
+
+        let amount = match deposit_account.betting_state {
+            BETTING_STATE_REFUNDED => fill.amount,
+            BETTING_STATE_FINALIZED if deposit_account.winner_auth == Pubkey::default() => {
+                (fill.amount as u128 * deposit_account.total_payout as u128 / deposit_account.filled_amount as u128) as u64
+            }
+            // takers lost, the record is only closed to reclaim its rent
+            BETTING_STATE_FINALIZED => 0,
+            BETTING_STATE_SETTLED => return Err(Errors::DisputeWindowOpen.into()),
+            _ => return Err(Errors::InvalidBettingState.into()),
+        };
+
+        if amount > 0 {
+            let sys_program = &ctx.accounts.system_program;
+            let pda_auth = &ctx.accounts.pda_auth;
+            let sol_vault = &ctx.accounts.sol_vault;
+
+            let cpi_accounts = system_program::Transfer {
+                from: sol_vault.to_account_info(),
+                to: ctx.accounts.taker_auth.to_account_info(),
+            };
+
+            let seeds = &[
+                b"sol_vault",
+                pda_auth.to_account_info().key.as_ref(),
+                &[deposit_account.sol_vault_bump.unwrap()],
+            ];
+
+            let signer = &[&seeds[..]];
+
+            let cpi = CpiContext::new_with_signer(sys_program.to_account_info(), cpi_accounts, signer);
+
+            system_program::transfer(cpi, amount)?;
+        }
+
+        Ok(())
+    }
+
+    // maker takes back the part of their stake no taker matched once the option has expired
+    pub fn refund_unfilled(ctx: Context<RefundUnfilled>) -> Result<()> {
+        let deposit_account = &mut ctx.accounts.deposit_account;
+
+        if Clock::get()?.unix_timestamp < deposit_account.expiry {
+            return Err(Errors::OptionNotExpired.into());
+        }
+        if deposit_account.unfilled_refunded {
+            return Err(Errors::AlreadyWithdrawn.into());
+        }
+
+        let (matched_stake, _) = deposit_account.matched_stakes();
+        let amount = deposit_account.bet_amount - matched_stake;
+        if amount == 0 {
+            return Err(Errors::NothingToClaim.into());
+        }
+
+        deposit_account.unfilled_refunded = true;
+
+        let sys_program = &ctx.accounts.system_program;
+        let pda_auth = &ctx.accounts.pda_auth;
+        let sol_vault = &ctx.accounts.sol_vault;
+
+        let cpi_accounts = system_program::Transfer {
+            from: sol_vault.to_account_info(),
+            to: ctx.accounts.deposit_auth.to_account_info(),
+        };
+
+        let seeds = &[
+            b"sol_vault",
+            pda_auth.to_account_info().key.as_ref(),
+            &[deposit_account.sol_vault_bump.unwrap()],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        let cpi = CpiContext::new_with_signer(sys_program.to_account_info(), cpi_accounts, signer);
+
+        system_program::transfer(cpi, amount)?;
+
+        Ok(())
+    }
+
+    // first participant cancels an option nobody has accepted yet and takes back the bet amount
+    pub fn cancel_binary_options(ctx: Context<CancelBinaryOptions>) -> Result<()> {
+        let deposit_account = &ctx.accounts.deposit_account;
+
+        unlist(deposit_account, &ctx.accounts.order_book)?;
+
+        let sys_program = &ctx.accounts.system_program;
+        let pda_auth = &ctx.accounts.pda_auth;
+        let sol_vault = &ctx.accounts.sol_vault;
+
+        let cpi_accounts = system_program::Transfer {
+            from: sol_vault.to_account_info(),
+            to: ctx.accounts.deposit_auth.to_account_info(),
+        };
+
+        let seeds = &[
+            b"sol_vault",
+            pda_auth.to_account_info().key.as_ref(),
+            &[deposit_account.sol_vault_bump.unwrap()],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        let cpi = CpiContext::new_with_signer(sys_program.to_account_info(), cpi_accounts, signer);
+
+        system_program::transfer(cpi, deposit_account.bet_amount)?;
+
+        Ok(())
+    }
+
     // withdraw native sol 
     pub fn withdraw_participant_funds(ctx: Context<WithdrawParticipantFunds>, amount: u64) -> Result<()> {
         let valid_amount = {
@@ -200,10 +490,10 @@ pub mod binary_options {
         };
         // amount must be greater than zero
         if !valid_amount {
-            return Err(Errors::AmountNotgreaterThanZero.into());
+            return Err(Errors::InvalidWithdrawAmount.into());
         }
 
-        let deposit_account = &ctx.accounts.deposit_account;
+        let deposit_account = &mut ctx.accounts.deposit_account;
         let deposit_auth = &ctx.accounts.deposit_auth;
 
         let valid_participant_key = {
@@ -224,28 +514,79 @@ pub mod binary_options {
             return Err(Errors::InvalidPrediction.into());
         }
 
-        let valid_participant_winner = {
-            // This is a check to determine the person withdrawing is the one who won the prediction
-            if deposit_account.winner_auth.eq(deposit_auth.key) {
-                true
-            }
-            else{false}
-        };
+        let is_first_participant = deposit_account.deposit_auth.eq(deposit_auth.key);
 
-        if !valid_participant_winner {
-            // Invalid participant winner.
-            return Err(Errors::InvalidWinner.into());
+        if deposit_account.betting_state == BETTING_STATE_REFUNDED {
+            // settlement was voided, so each participant takes back exactly what they deposited
+            let (stake, withdrawn) = if is_first_participant {
+                (deposit_account.maker_refundable_stake(), deposit_account.maker_withdrawn)
+            }
+            else {
+                (deposit_account.taker_amount, deposit_account.taker_withdrawn)
+            };
+            if withdrawn {
+                return Err(Errors::AlreadyWithdrawn.into());
+            }
+            if amount != stake {
+                return Err(Errors::AmountNotEqualToStake.into());
+            }
+            // the unfilled part of the stake was part of this refund
+            if is_first_participant {
+                deposit_account.unfilled_refunded = true;
+            }
         }
+        else {
+            // payout is only claimable once the dispute window (if any) has passed
+            if deposit_account.betting_state == BETTING_STATE_SETTLED {
+                return Err(Errors::DisputeWindowOpen.into());
+            }
+            if deposit_account.betting_state != BETTING_STATE_FINALIZED {
+                return Err(Errors::InvalidBettingState.into());
+            }
+            if deposit_account.maker_withdrawn || deposit_account.taker_withdrawn {
+                return Err(Errors::AlreadyWithdrawn.into());
+            }
+            // payout is waiting to be restaked into the next round, the winner must opt out first
+            let rollover = if is_first_participant {
+                deposit_account.maker_rollover
+            }
+            else {
+                deposit_account.taker_rollover
+            };
+            if rollover {
+                return Err(Errors::RolloverEnabled.into());
+            }
 
-        let valid_amount = {
-            if amount == deposit_account.total_payout {
-                true
+            let valid_participant_winner = {
+                // This is a check to determine the person withdrawing is the one who won the prediction
+                if deposit_account.winner_auth.eq(deposit_auth.key) {
+                    true
+                }
+                else{false}
+            };
+
+            if !valid_participant_winner {
+                // Invalid participant winner.
+                return Err(Errors::InvalidWinner.into());
             }
-            else{false}
-        };
-        // withdrawal amount must be equal to total payout amount
-        if !valid_amount {
-            return Err(Errors::AmountNotEqualToTotalPayoutAmount.into());
+
+            let valid_amount = {
+                if amount == deposit_account.total_payout {
+                    true
+                }
+                else{false}
+            };
+            // withdrawal amount must be equal to total payout amount
+            if !valid_amount {
+                return Err(Errors::AmountNotEqualToTotalPayoutAmount.into());
+            }
+        }
+
+        if is_first_participant {
+            deposit_account.maker_withdrawn = true;
+        }
+        else {
+            deposit_account.taker_withdrawn = true;
         }
 
         let sys_program = &ctx.accounts.system_program;
@@ -273,24 +614,31 @@ pub mod binary_options {
         Ok(())
     }
 
-    pub fn process_prediction(ctx: Context<ProcessPrediction>, bet_fees: u64) -> Result<()> {
-        let valid_amount = {
-            if bet_fees > 0 {
-                true
-            }
-            else{false}
-        };
-        // amount must be greater than zero
-        if !valid_amount {
-            return Err(Errors::AmountNotgreaterThanZero.into());
-        }
-
+    pub fn process_prediction(ctx: Context<ProcessPrediction>) -> Result<()> {
         // Test Pyth oracle price feeds
         let price_feed = &ctx.accounts.pyth_price_feed_account;
         let current_timestamp1 = Clock::get()?.unix_timestamp;
-        let current_price = price_feed
-            .get_price_no_older_than(current_timestamp1, STALENESS_THRESHOLD)
-            .ok_or(Errors::PythOffline)?;
+
+        let expiry = ctx.accounts.deposit_account.expiry;
+        if expiry > 0 && current_timestamp1 < expiry {
+            return Err(Errors::OptionNotExpired.into());
+        }
+
+        let current_price = match ctx.accounts.deposit_account.settlement_mode {
+            SettlementMode::Spot => price_feed
+                .get_price_no_older_than(current_timestamp1, STALENESS_THRESHOLD)
+                .ok_or(Errors::PythOffline)?,
+            SettlementMode::Ema => price_feed
+                .get_ema_price_no_older_than(current_timestamp1, STALENESS_THRESHOLD)
+                .ok_or(Errors::PythOffline)?,
+            // an option nobody observed settles on the EMA instead of staying locked, unless that is stale too
+            SettlementMode::Twap => match ctx.accounts.deposit_account.twap_price()? {
+                Some(twap_price) => twap_price,
+                None => price_feed
+                    .get_ema_price_no_older_than(current_timestamp1, STALENESS_THRESHOLD)
+                    .ok_or(Errors::NotEnoughObservations)?,
+            },
+        };
 
         let deposit_account = &mut ctx.accounts.deposit_account;
         let pda_auth = &mut ctx.accounts.pda_auth;
@@ -357,20 +705,10 @@ pub mod binary_options {
             else {false}
         };
 
-        let bet_amount = deposit_account.bet_amount;
-        let taker_amount = deposit_account.taker_amount;
+        // only the part of the maker's stake that was matched by takers is at play
+        let (bet_amount, taker_amount) = deposit_account.matched_stakes();
 
-        let valid_amount = {
-            if bet_amount + taker_amount > bet_fees  {
-                true
-            }
-            else{false}
-        };
-        // bet_fees exceeds (bet_amount + taker_amount)
-        if !valid_amount {
-            return Err(Errors::InvalidWinningAmount.into());
-        }
-        
+        let bet_fees = settlement_fee(bet_amount + taker_amount, ctx.accounts.config.fee_bps)?;
         let total_payout: u64 = bet_amount + taker_amount - bet_fees;
         let mut valid_position = false;
         // first_participant - deposit_account.deposit_auth
@@ -396,75 +734,411 @@ pub mod binary_options {
             deposit_account.total_payout = total_payout;
             valid_position = true;
         }
+        else if deposit_account.filled_amount > 0 {
+            // takers share the payout in proportion to their fills, see claim_fill
+            deposit_account.winner_auth = Pubkey::default();
+            deposit_account.total_payout = total_payout;
+            valid_position = true;
+        }
         else {
             deposit_account.winner_auth = deposit_account.taker_auth;
             deposit_account.total_payout = total_payout;
             valid_position = true;
         }
 
+        deposit_account.bet_fees = bet_fees;
+        deposit_account.settled_at = current_timestamp1;
+        // the dispute window is a market setting, recorded on the option for finalize and void
+        deposit_account.dispute_window = ctx.accounts.config.dispute_window;
+
         if valid_position {
-            // step 1: deposit (bet_fees) sol to admin vault
-            let cpi_accounts = system_program::Transfer {
-                from: sol_vault.to_account_info(),
-                to: ctx.accounts.admin_sol_vault.to_account_info(),
-            };
+            if deposit_account.dispute_window > 0 {
+                // payout and fees stay in the vault until the dispute window has passed
+                deposit_account.betting_state = BETTING_STATE_SETTLED;
+            }
+            else {
+                distribute_fees(
+                    deposit_account,
+                    pda_auth,
+                    sol_vault,
+                    &ctx.accounts.admin_sol_vault,
+                    &mut ctx.accounts.maker_referrer,
+                    &mut ctx.accounts.taker_referrer,
+                    sys_program,
+                    ctx.accounts.config.referral_fee_bps,
+                )?;
+                deposit_account.betting_state = BETTING_STATE_FINALIZED;
+            }
+        }
+        
+        Ok(())
+    }
 
-            let seeds = &[
-                b"sol_vault",
-                pda_auth.to_account_info().key.as_ref(),
-                &[deposit_account.sol_vault_bump.unwrap()],
-            ];
+    // anyone can finalize a settlement once its dispute window has passed, releasing the fees and the payout
+    pub fn finalize_settlement(ctx: Context<FinalizeSettlement>) -> Result<()> {
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        let deposit_account = &mut ctx.accounts.deposit_account;
 
-            let signer = &[&seeds[..]];
+        let dispute_window_end = deposit_account.settled_at
+            .checked_add(deposit_account.dispute_window)
+            .ok_or(Errors::Overflow)?;
+        if current_timestamp < dispute_window_end {
+            return Err(Errors::DisputeWindowOpen.into());
+        }
 
-            let cpi = CpiContext::new_with_signer(sys_program.to_account_info(), cpi_accounts, signer);
+        distribute_fees(
+            deposit_account,
+            &ctx.accounts.pda_auth,
+            &ctx.accounts.sol_vault,
+            &ctx.accounts.admin_sol_vault,
+            &mut ctx.accounts.maker_referrer,
+            &mut ctx.accounts.taker_referrer,
+            &ctx.accounts.system_program,
+            ctx.accounts.config.referral_fee_bps,
+        )?;
+        deposit_account.betting_state = BETTING_STATE_FINALIZED;
 
-            system_program::transfer(cpi, bet_fees)?;
-        }
-        
         Ok(())
     }
 
-    // admin (on behalf of house) withdraws native sol 
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
-        let sys_program = &ctx.accounts.system_program;
-        let deposit_account = &ctx.accounts.admin_deposit_account;
-        let pda_auth = &mut ctx.accounts.admin_pda_auth;
-        let sol_vault = &mut ctx.accounts.admin_sol_vault;
+    // admin (or the designated resolver) voids a settlement that used an anomalous price during its dispute window.
+    // the option can then either be processed again or refunded to both participants.
+    pub fn void_settlement(ctx: Context<VoidSettlement>, refund: bool) -> Result<()> {
+        let admin_deposit_account = &ctx.accounts.admin_deposit_account;
+        let resolver_auth = ctx.accounts.resolver_auth.key;
 
-        let cpi_accounts = system_program::Transfer {
-            from: sol_vault.to_account_info(),
-            to: ctx.accounts.admin_auth.to_account_info(),
-        };
+        let valid_resolver = admin_deposit_account.admin_auth.eq(resolver_auth)
+            || admin_deposit_account.resolver == Some(*resolver_auth);
+        if !valid_resolver {
+            return Err(Errors::Unauthorized.into());
+        }
 
-        let seeds = &[
-            b"admin_sol_vault",
-            pda_auth.to_account_info().key.as_ref(),
-            &[deposit_account.admin_sol_vault_bump.unwrap()],
-        ];
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        let deposit_account = &mut ctx.accounts.deposit_account;
 
-        let signer = &[&seeds[..]];
+        let dispute_window_end = deposit_account.settled_at
+            .checked_add(deposit_account.dispute_window)
+            .ok_or(Errors::Overflow)?;
+        if current_timestamp >= dispute_window_end {
+            return Err(Errors::DisputeWindowClosed.into());
+        }
 
-        let cpi = CpiContext::new_with_signer(sys_program.to_account_info(), cpi_accounts, signer);
+        // nothing has left the vault yet, so clearing the settlement is enough to undo it
+        deposit_account.winner_auth = Pubkey::default();
+        deposit_account.total_payout = 0;
+        deposit_account.bet_fees = 0;
+        deposit_account.settled_at = 0;
+        deposit_account.betting_state = if refund {
+            BETTING_STATE_REFUNDED
+        }
+        else {
+            BETTING_STATE_ACCEPTED
+        };
 
-        system_program::transfer(cpi, amount)?;
+        Ok(())
+    }
+
+    // admin designates (or removes) the resolver allowed to void settlements alongside the admin
+    pub fn set_resolver(ctx: Context<SetResolver>, resolver: Option<Pubkey>) -> Result<()> {
+        ctx.accounts.admin_deposit_account.resolver = resolver;
 
         Ok(())
     }
 
-}
+    // partners register once to be able to collect referral fees
+    pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+        let referrer = &mut ctx.accounts.referrer;
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
+        referrer.referrer_auth = *ctx.accounts.referrer_auth.key;
+        referrer.bump = *ctx.bumps.get("referrer").unwrap();
+        referrer.accrued_fees = 0;
+        referrer.claimed_fees = 0;
+
+        Ok(())
+    }
+
+    // referrer withdraws all referral fees credited to them so far
+    pub fn claim_referral_fees(ctx: Context<ClaimReferralFees>) -> Result<()> {
+        let referrer = &mut ctx.accounts.referrer;
+
+        let amount = referrer.accrued_fees
+            .checked_sub(referrer.claimed_fees)
+            .ok_or(Errors::Overflow)?;
+        if amount == 0 {
+            return Err(Errors::NothingToClaim.into());
+        }
+
+        referrer.claimed_fees = referrer.accrued_fees;
+
+        // the referrer account is owned by this program, so its lamports can be debited directly
+        **referrer.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.referrer_auth.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        Ok(())
+    }
+
+    // creates the index of open options for the market of a config
+    pub fn init_order_book(ctx: Context<InitOrderBook>) -> Result<()> {
+        let mut order_book = ctx.accounts.order_book.load_init()?;
+        order_book.config = ctx.accounts.config.key();
+
+        Ok(())
+    }
+
+    // keeper call evicting expired offers from an order book, along with the options passed in as remaining
+    // accounts that are no longer open
+    pub fn prune_order_book(ctx: Context<PruneOrderBook>) -> Result<()> {
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        let order_book_key = ctx.accounts.order_book.key();
+
+        let mut closed = Vec::new();
+        for account in ctx.remaining_accounts {
+            let open = account.owner == ctx.program_id &&
+                BinaryOption::try_deserialize(&mut &account.try_borrow_data()?[..])
+                    .map(|option| {
+                        option.betting_state == BETTING_STATE_OPEN && option.order_book == Some(order_book_key)
+                    })
+                    .unwrap_or(false);
+            if !open {
+                closed.push(account.key());
+            }
+        }
+
+        ctx.accounts.order_book.load_mut()?.prune(|listed| {
+            (listed.expiry > 0 && listed.expiry <= current_timestamp) || closed.contains(&listed.option)
+        });
+
+        Ok(())
+    }
+
+    // participant opts in or out of restaking their payout into the next round of the same market
+    pub fn set_rollover(ctx: Context<SetRollover>, enabled: bool) -> Result<()> {
+        let deposit_account = &mut ctx.accounts.deposit_account;
+        let participant = ctx.accounts.participant.key;
+
+        // only options with an expiry recur, the next round expires one round_duration later
+        if enabled && deposit_account.round_duration == 0 {
+            return Err(Errors::RolloverUnavailable.into());
+        }
+
+        if deposit_account.deposit_auth.eq(participant) {
+            deposit_account.maker_rollover = enabled;
+        }
+        else if deposit_account.made_prediction && deposit_account.taker_auth.eq(participant) {
+            deposit_account.taker_rollover = enabled;
+        }
+        else {
+            return Err(Errors::WithdrawalDisAllowed.into());
+        }
+
+        Ok(())
+    }
+
+    // keeper call restaking the payout of a finalized option into the next round with the winner as maker.
+    // Each option is a two-party bet without a pool, so the loser forfeits their whole stake and only the
+    // winner has a balance left to roll over.
+    pub fn rollover_round(ctx: Context<RolloverRound>) -> Result<()> {
+        let deposit_account = &mut ctx.accounts.deposit_account;
+        let next_round = &mut ctx.accounts.next_round;
+        let sys_program = &ctx.accounts.system_program;
+
+        let winner_is_maker = deposit_account.winner_auth.eq(&deposit_account.deposit_auth);
+        let (rollover, position, referrer, winner_stake, other_stake) = if winner_is_maker {
+            (
+                deposit_account.maker_rollover,
+                deposit_account.first_participant,
+                deposit_account.maker_referrer,
+                deposit_account.bet_amount,
+                deposit_account.taker_amount,
+            )
+        }
+        else {
+            (
+                deposit_account.taker_rollover,
+                deposit_account.second_participant,
+                deposit_account.taker_referrer,
+                deposit_account.taker_amount,
+                deposit_account.bet_amount,
+            )
+        };
+        if !rollover {
+            return Err(Errors::RolloverDisabled.into());
+        }
+        if deposit_account.maker_withdrawn || deposit_account.taker_withdrawn {
+            return Err(Errors::AlreadyWithdrawn.into());
+        }
+
+        // the counterparty of the next round is asked for the same odds as this one
+        let bet_amount = deposit_account.total_payout;
+        let taker_amount = (other_stake as u128 * bet_amount as u128 / winner_stake as u128) as u64;
+        if taker_amount == 0 {
+            return Err(Errors::InvalidTakerAmount.into());
+        }
+
+        // rounds that were missed while nobody cranked are skipped
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        let round_duration = deposit_account.round_duration;
+        let mut expiry = deposit_account.expiry.checked_add(round_duration).ok_or(Errors::Overflow)?;
+        if expiry <= current_timestamp {
+            let missed_rounds = (current_timestamp - expiry) / round_duration + 1;
+            expiry = missed_rounds
+                .checked_mul(round_duration)
+                .and_then(|skipped| expiry.checked_add(skipped))
+                .ok_or(Errors::Overflow)?;
+        }
+
+        next_round.deposit_auth = deposit_account.winner_auth;
+        next_round.taker_auth = deposit_account.winner_auth;
+        next_round.auth_bump = *ctx.bumps.get("next_pda_auth").unwrap();
+        next_round.sol_vault_bump = ctx.bumps.get("next_sol_vault").copied();
+        next_round.bet_description = deposit_account.bet_description.clone();
+        next_round.bet_amount = bet_amount;
+        next_round.strike_price = deposit_account.strike_price;
+        next_round.taker_amount = taker_amount;
+        next_round.first_participant = position;
+        next_round.betting_state = BETTING_STATE_OPEN;
+        next_round.maker_referrer = referrer;
+        // the dispute window is not copied, the round is held to the market's window when it settles
+        next_round.settlement_mode = deposit_account.settlement_mode;
+        next_round.expiry = expiry;
+        next_round.round_duration = deposit_account.round_duration;
+        next_round.maker_rollover = true;
+
+        // the next round is offered to takers in the book the previous one was listed in, as long as
+        // it still stakes enough to be listed
+        if let Some(listed_in) = deposit_account.order_book.filter(|_| bet_amount >= MIN_LISTING_AMOUNT) {
+            let order_book = match &ctx.accounts.order_book {
+                Some(order_book) if order_book.key() == listed_in => order_book,
+                _ => return Err(Errors::OrderBookMissing.into()),
+            };
+            let maker_position = match position {
+                ParticipantPosition::Long => POSITION_LONG,
+                _ => POSITION_SHORT,
+            };
+            order_book.load_mut()?.insert(OrderBookEntry {
+                option: next_round.key(),
+                strike_price: next_round.strike_price,
+                expiry,
+                bet_amount,
+                taker_amount,
+                maker_position,
+                ..OrderBookEntry::default()
+            })?;
+            next_round.order_book = Some(listed_in);
+        }
+
+        if winner_is_maker {
+            deposit_account.maker_withdrawn = true;
+        }
+        else {
+            deposit_account.taker_withdrawn = true;
+        }
+        deposit_account.next_round = Some(next_round.key());
+
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.sol_vault.to_account_info(),
+            to: ctx.accounts.next_sol_vault.to_account_info(),
+        };
+
+        let seeds = &[
+            b"sol_vault",
+            ctx.accounts.pda_auth.to_account_info().key.as_ref(),
+            &[deposit_account.sol_vault_bump.unwrap()],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        let cpi = CpiContext::new_with_signer(sys_program.to_account_info(), cpi_accounts, signer);
+
+        system_program::transfer(cpi, bet_amount)?;
+
+        Ok(())
+    }
+
+    // keeper call recording the spot price into the TWAP of an option during the window before expiry
+    pub fn observe_price(ctx: Context<ObservePrice>) -> Result<()> {
+        let price_feed = &ctx.accounts.pyth_price_feed_account;
+        let deposit_account = &mut ctx.accounts.deposit_account;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        if deposit_account.settlement_mode != SettlementMode::Twap {
+            return Err(Errors::InvalidSettlementMode.into());
+        }
+        if current_timestamp < deposit_account.expiry - TWAP_WINDOW || current_timestamp > deposit_account.expiry {
+            return Err(Errors::OutsideObservationWindow.into());
+        }
+        if deposit_account.twap_last_observed > 0 &&
+            current_timestamp - deposit_account.twap_last_observed < MIN_OBSERVATION_INTERVAL {
+            return Err(Errors::ObservationTooSoon.into());
+        }
+
+        let current_price = price_feed
+            .get_price_no_older_than(current_timestamp, STALENESS_THRESHOLD)
+            .ok_or(Errors::PythOffline)?;
+
+        deposit_account.record_observation(current_price.price, current_price.expo, current_timestamp)
+    }
+
+    // grows a binary option created with an older layout to the current one
+    pub fn migrate_binary_option(ctx: Context<MigrateBinaryOption>) -> Result<()> {
+        grow_account::<BinaryOption>(
+            &ctx.accounts.deposit_account,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            DISCRIMINATOR_LENGTH + BinaryOption::INIT_SPACE,
+        )
+    }
+
+    // grows the admin deposit account created with an older layout to the current one
+    pub fn migrate_admin_deposit_account(ctx: Context<MigrateAdminDepositAccount>) -> Result<()> {
+        grow_account::<DepositBaseAdmin>(
+            &ctx.accounts.admin_deposit_account,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            DISCRIMINATOR_LENGTH + DepositBaseAdmin::INIT_SPACE,
+        )
+    }
+
+    // admin (on behalf of house) withdraws native sol 
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        let sys_program = &ctx.accounts.system_program;
+        let deposit_account = &ctx.accounts.admin_deposit_account;
+        let pda_auth = &mut ctx.accounts.admin_pda_auth;
+        let sol_vault = &mut ctx.accounts.admin_sol_vault;
+
+        let cpi_accounts = system_program::Transfer {
+            from: sol_vault.to_account_info(),
+            to: ctx.accounts.admin_auth.to_account_info(),
+        };
+
+        let seeds = &[
+            b"admin_sol_vault",
+            pda_auth.to_account_info().key.as_ref(),
+            &[deposit_account.admin_sol_vault_bump.unwrap()],
+        ];
+
+        let signer = &[&seeds[..]];
+
+        let cpi = CpiContext::new_with_signer(sys_program.to_account_info(), cpi_accounts, signer);
+
+        system_program::transfer(cpi, amount)?;
+
+        Ok(())
+    }
+
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
     // Pyth Oracle price feeds accounts
     #[account(address = *program_id @ Errors::Unauthorized)]
     pub program: Signer<'info>,
     //#[account(mut)]
     //pub payer: Signer<'info>,
-    #[account(init, payer = admin_auth, space = 8 + size_of::<AdminConfig>())]
+    #[account(init, payer = admin_auth, space = DISCRIMINATOR_LENGTH + AdminConfig::INIT_SPACE)]
     pub config: Account<'info, AdminConfig>,
     //
-    #[account(init, payer = admin_auth, space = DepositBaseAdmin::LEN,
+    #[account(init, payer = admin_auth, space = DISCRIMINATOR_LENGTH + DepositBaseAdmin::INIT_SPACE,
         constraint = !admin_deposit_account.is_initialized @ Errors::AccountAlreadyInitialized
     )]
     pub admin_deposit_account: Account<'info, DepositBaseAdmin>,
@@ -480,7 +1154,9 @@ pub struct Initialize<'info> {
 
 #[derive(Accounts)]
 pub struct CreateBinaryOptions<'info> {
-    #[account(init, payer = deposit_auth, space = BinaryOption::LEN)]
+    // market the option is created in, deciding how it settles
+    pub config: Account<'info, AdminConfig>,
+    #[account(init, payer = deposit_auth, space = DISCRIMINATOR_LENGTH + BinaryOption::INIT_SPACE)]
     pub deposit_account: Account<'info, BinaryOption>,
     #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump)]
     /// CHECK: no need to check this.
@@ -494,7 +1170,12 @@ pub struct CreateBinaryOptions<'info> {
         constraint = admin_deposit_account.is_initialized @ Errors::AccountNotInitialized
     )]
     pub admin_deposit_account: Account<'info, DepositBaseAdmin>,
-    //
+    // only the book of the market the option is created in
+    #[account(mut, seeds = [b"order_book", config.key().as_ref()], bump)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+    // only required when the maker was referred
+    #[account(seeds = [b"referrer", referrer.referrer_auth.as_ref()], bump = referrer.bump)]
+    pub referrer: Option<Account<'info, Referrer>>,
     pub system_program: Program<'info, System>,
 }
 
@@ -512,7 +1193,7 @@ pub struct AcceptBinaryOptions<'info> {
     pub admin_sol_vault: SystemAccount<'info>,
     //admin accs
     #[account(mut,
-        constraint = deposit_account.betting_state == 1 @ Errors::InvalidParticipantsLimit,
+        constraint = deposit_account.betting_state == BETTING_STATE_OPEN @ Errors::InvalidParticipantsLimit,
     )]
     pub deposit_account: Account<'info, BinaryOption>,
     #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
@@ -522,11 +1203,95 @@ pub struct AcceptBinaryOptions<'info> {
     pub sol_vault: SystemAccount<'info>,
     #[account(mut)]
     pub deposit_auth: Signer<'info>,
+    // only required when the option was listed
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+    // only required when the taker was referred
+    #[account(seeds = [b"referrer", referrer.referrer_auth.as_ref()], bump = referrer.bump)]
+    pub referrer: Option<Account<'info, Referrer>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FillBinaryOptions<'info> {
+    #[account(mut,
+        constraint = deposit_account.betting_state == BETTING_STATE_OPEN @ Errors::InvalidParticipantsLimit,
+    )]
+    pub deposit_account: Account<'info, BinaryOption>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"sol_vault", pda_auth.key().as_ref()], bump = deposit_account.sol_vault_bump.unwrap())]
+    pub sol_vault: SystemAccount<'info>,
+    #[account(init, payer = taker_auth, space = DISCRIMINATOR_LENGTH + Fill::INIT_SPACE,
+        seeds = [b"fill", deposit_account.key().as_ref(), taker_auth.key().as_ref()], bump
+    )]
+    pub fill: Account<'info, Fill>,
+    #[account(mut)]
+    pub taker_auth: Signer<'info>,
+    // only required when the option was listed
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimFill<'info> {
+    pub deposit_account: Account<'info, BinaryOption>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"sol_vault", pda_auth.key().as_ref()], bump = deposit_account.sol_vault_bump.unwrap())]
+    pub sol_vault: SystemAccount<'info>,
+    #[account(mut, has_one = deposit_account, has_one = taker_auth, close = taker_auth,
+        seeds = [b"fill", deposit_account.key().as_ref(), taker_auth.key().as_ref()], bump = fill.bump
+    )]
+    pub fill: Account<'info, Fill>,
+    #[account(mut)]
+    pub taker_auth: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefundUnfilled<'info> {
+    #[account(mut, has_one = deposit_auth,
+        constraint = deposit_account.filled_amount > 0 @ Errors::InvalidBettingState,
+    )]
+    pub deposit_account: Account<'info, BinaryOption>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"sol_vault", pda_auth.key().as_ref()], bump = deposit_account.sol_vault_bump.unwrap())]
+    pub sol_vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub deposit_auth: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelBinaryOptions<'info> {
+    #[account(mut, has_one = deposit_auth, close = deposit_auth,
+        constraint = deposit_account.betting_state == BETTING_STATE_OPEN @ Errors::InvalidBettingState,
+        // filled stakes belong to the takers until settlement
+        constraint = deposit_account.filled_amount == 0 @ Errors::OptionPartiallyFilled,
+    )]
+    pub deposit_account: Account<'info, BinaryOption>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"sol_vault", pda_auth.key().as_ref()], bump = deposit_account.sol_vault_bump.unwrap())]
+    pub sol_vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub deposit_auth: Signer<'info>,
+    // only required when the option was listed
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 pub struct WithdrawParticipantFunds<'info> {
+    #[account(mut)]
     pub deposit_account: Account<'info, BinaryOption>,
     #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
     /// CHECK: no need to check this.
@@ -542,12 +1307,16 @@ pub struct WithdrawParticipantFunds<'info> {
 pub struct ProcessPrediction<'info> {
     // Pyth Oracle price feeds accounts
     pub config: Account<'info, AdminConfig>,
-    #[account(address = config.price_feed_id @ Errors::InvalidArgument)]
+    #[account(address = config.price_feed_id @ Errors::InvalidPriceFeed)]
     pub pyth_price_feed_account: Account<'info, PriceFeed>,
-    //#[account(address = config.collateral_price_feed_id @ Errors::InvalidArgument)]
+    //#[account(address = config.collateral_price_feed_id @ Errors::InvalidPriceFeed)]
     //pub pyth_collateral_account: Account<'info, PriceFeed>,
     //
-    #[account(mut)]
+    #[account(mut,
+        // partially filled options settle at expiry
+        constraint = deposit_account.betting_state == BETTING_STATE_ACCEPTED ||
+            (deposit_account.betting_state == BETTING_STATE_OPEN && deposit_account.filled_amount > 0) @ Errors::InvalidBettingState,
+    )]
     pub deposit_account: Account<'info, BinaryOption>,
     #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
     /// CHECK: no need to check this.
@@ -565,6 +1334,174 @@ pub struct ProcessPrediction<'info> {
     #[account(mut, seeds = [b"admin_sol_vault", admin_pda_auth.key().as_ref()], bump = admin_deposit_account.admin_sol_vault_bump.unwrap())]
     pub admin_sol_vault: SystemAccount<'info>,
     //admin accs
+    //referrer accs, only required when the participant was referred
+    #[account(mut, seeds = [b"referrer", maker_referrer.referrer_auth.as_ref()], bump = maker_referrer.bump)]
+    pub maker_referrer: Option<Account<'info, Referrer>>,
+    #[account(mut, seeds = [b"referrer", taker_referrer.referrer_auth.as_ref()], bump = taker_referrer.bump)]
+    pub taker_referrer: Option<Account<'info, Referrer>>,
+    //referrer accs
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeSettlement<'info> {
+    pub config: Account<'info, AdminConfig>,
+    #[account(mut,
+        constraint = deposit_account.betting_state == BETTING_STATE_SETTLED @ Errors::InvalidBettingState,
+    )]
+    pub deposit_account: Account<'info, BinaryOption>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"sol_vault", pda_auth.key().as_ref()], bump = deposit_account.sol_vault_bump.unwrap())]
+    pub sol_vault: SystemAccount<'info>,
+    //admin accs
+    #[account(
+        constraint = admin_deposit_account.is_initialized @ Errors::AccountNotInitialized
+    )]
+    pub admin_deposit_account: Account<'info, DepositBaseAdmin>,
+    #[account(seeds = [b"admin_auth", admin_deposit_account.key().as_ref()], bump = admin_deposit_account.admin_auth_bump)]
+    /// CHECK: no need to check this.
+    pub admin_pda_auth: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"admin_sol_vault", admin_pda_auth.key().as_ref()], bump = admin_deposit_account.admin_sol_vault_bump.unwrap())]
+    pub admin_sol_vault: SystemAccount<'info>,
+    //admin accs
+    //referrer accs, only required when the participant was referred
+    #[account(mut, seeds = [b"referrer", maker_referrer.referrer_auth.as_ref()], bump = maker_referrer.bump)]
+    pub maker_referrer: Option<Account<'info, Referrer>>,
+    #[account(mut, seeds = [b"referrer", taker_referrer.referrer_auth.as_ref()], bump = taker_referrer.bump)]
+    pub taker_referrer: Option<Account<'info, Referrer>>,
+    //referrer accs
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VoidSettlement<'info> {
+    #[account(mut,
+        constraint = deposit_account.betting_state == BETTING_STATE_SETTLED @ Errors::InvalidBettingState,
+    )]
+    pub deposit_account: Account<'info, BinaryOption>,
+    #[account(
+        constraint = admin_deposit_account.is_initialized @ Errors::AccountNotInitialized
+    )]
+    pub admin_deposit_account: Account<'info, DepositBaseAdmin>,
+    pub resolver_auth: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetResolver<'info> {
+    #[account(mut, has_one = admin_auth)]
+    pub admin_deposit_account: Account<'info, DepositBaseAdmin>,
+    pub admin_auth: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterReferrer<'info> {
+    #[account(init, payer = referrer_auth, space = DISCRIMINATOR_LENGTH + Referrer::INIT_SPACE,
+        seeds = [b"referrer", referrer_auth.key().as_ref()], bump
+    )]
+    pub referrer: Account<'info, Referrer>,
+    #[account(mut)]
+    pub referrer_auth: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralFees<'info> {
+    #[account(mut, has_one = referrer_auth,
+        seeds = [b"referrer", referrer_auth.key().as_ref()], bump = referrer.bump
+    )]
+    pub referrer: Account<'info, Referrer>,
+    #[account(mut)]
+    pub referrer_auth: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitOrderBook<'info> {
+    pub config: Account<'info, AdminConfig>,
+    #[account(init, payer = payer, space = DISCRIMINATOR_LENGTH + std::mem::size_of::<OrderBook>(),
+        seeds = [b"order_book", config.key().as_ref()], bump
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PruneOrderBook<'info> {
+    #[account(mut)]
+    pub order_book: AccountLoader<'info, OrderBook>,
+}
+
+#[derive(Accounts)]
+pub struct SetRollover<'info> {
+    #[account(mut,
+        constraint = deposit_account.betting_state != BETTING_STATE_REFUNDED @ Errors::InvalidBettingState,
+        constraint = deposit_account.next_round.is_none() @ Errors::AlreadyRolledOver,
+    )]
+    pub deposit_account: Account<'info, BinaryOption>,
+    pub participant: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RolloverRound<'info> {
+    #[account(mut,
+        constraint = deposit_account.betting_state == BETTING_STATE_FINALIZED @ Errors::InvalidBettingState,
+        constraint = deposit_account.next_round.is_none() @ Errors::AlreadyRolledOver,
+    )]
+    pub deposit_account: Box<Account<'info, BinaryOption>>,
+    #[account(seeds = [b"auth", deposit_account.key().as_ref()], bump = deposit_account.auth_bump)]
+    /// CHECK: no need to check this.
+    pub pda_auth: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"sol_vault", pda_auth.key().as_ref()], bump = deposit_account.sol_vault_bump.unwrap())]
+    pub sol_vault: SystemAccount<'info>,
+    #[account(init, payer = payer, space = DISCRIMINATOR_LENGTH + BinaryOption::INIT_SPACE,
+        seeds = [b"next_round", deposit_account.key().as_ref()], bump
+    )]
+    pub next_round: Box<Account<'info, BinaryOption>>,
+    #[account(seeds = [b"auth", next_round.key().as_ref()], bump)]
+    /// CHECK: no need to check this.
+    pub next_pda_auth: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"sol_vault", next_pda_auth.key().as_ref()], bump)]
+    pub next_sol_vault: SystemAccount<'info>,
+    // only required when the previous round was listed
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ObservePrice<'info> {
+    pub config: Account<'info, AdminConfig>,
+    #[account(address = config.price_feed_id @ Errors::InvalidPriceFeed)]
+    pub pyth_price_feed_account: Account<'info, PriceFeed>,
+    #[account(mut,
+        constraint = deposit_account.betting_state == BETTING_STATE_OPEN ||
+            deposit_account.betting_state == BETTING_STATE_ACCEPTED @ Errors::InvalidBettingState,
+    )]
+    pub deposit_account: Account<'info, BinaryOption>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateBinaryOption<'info> {
+    #[account(mut, owner = crate::ID)]
+    /// CHECK: older layouts do not deserialize as BinaryOption, the discriminator is checked by hand.
+    pub deposit_account: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAdminDepositAccount<'info> {
+    #[account(mut, owner = crate::ID)]
+    /// CHECK: older layouts do not deserialize as DepositBaseAdmin, the discriminator is checked by hand.
+    pub admin_deposit_account: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
@@ -582,13 +1519,19 @@ pub struct Withdraw<'info> {
     pub system_program: Program<'info, System>,
 }
 
+// Account sizes are derived with InitSpace. New fields must only ever be appended, so accounts created
+// with an older layout can be grown in place by the migrate instructions.
+const DISCRIMINATOR_LENGTH: usize = 8;
+
 #[account]
+#[derive(InitSpace)]
 pub struct BinaryOption {
     pub deposit_auth: Pubkey,
     pub taker_auth: Pubkey,
     pub winner_auth: Pubkey,
     pub auth_bump: u8,
     pub sol_vault_bump: Option<u8>,
+    #[max_len(DESCRIPTION_LENGTH)]
     pub bet_description: String,
     pub bet_amount: u64,
     pub taker_amount: u64,
@@ -602,50 +1545,351 @@ pub struct BinaryOption {
     pub first_participant: ParticipantPosition,
     pub second_participant: ParticipantPosition,
     pub betting_state: u8,
+    pub maker_referrer: Option<Pubkey>,
+    pub taker_referrer: Option<Pubkey>,
+    pub dispute_window: i64,
+    pub settled_at: i64,
+    pub bet_fees: u64,
+    pub maker_withdrawn: bool,
+    pub taker_withdrawn: bool,
+    pub settlement_mode: SettlementMode,
+    pub expiry: i64, // unix timestamp from which the option can be settled, zero when it has none
+    // time-weighted price accumulated by observe_price, in units of twap_expo
+    pub twap_cumulative: i128,
+    pub twap_duration: i64,
+    pub twap_last_price: i64,
+    pub twap_last_observed: i64,
+    pub twap_expo: i32,
+    pub round_duration: i64, // length of a round, the next round expires this long after expiry
+    pub maker_rollover: bool,
+    pub taker_rollover: bool,
+    pub next_round: Option<Pubkey>, // option the winner's payout was restaked into
+    pub order_book: Option<Pubkey>, // order book the option is listed in while open
+    pub filled_amount: u64, // taker stake received through fill_binary_options
+    pub unfilled_refunded: bool,
 }
 
-const DISCRIMINATOR_LENGTH: usize = 8;
-const PUBLIC_KEY_LENGTH: usize = 32;
-const U64_LENGTH: usize = 8;
-const U32_LENGTH: usize = 4;
-const U8_LENGTH: usize = 1;
-const BOOL_LENGTH: usize = 1;
-const OPTION_LENGTH: usize = 1; // 1 + (space(T))
-const ENUM_LENGTH: usize = 1; // 1 + Largest Variant Size
-
 impl BinaryOption {
-    const LEN: usize = DISCRIMINATOR_LENGTH +
-                       (PUBLIC_KEY_LENGTH * 3) +
-                       (1 + U8_LENGTH * 3) +
-                       DESCRIPTION_LENGTH +
-                       (U64_LENGTH * 7) +
-                       U32_LENGTH +
-                       BOOL_LENGTH +
-                       (ENUM_LENGTH + U8_LENGTH) +
-                       (ENUM_LENGTH + U8_LENGTH);
+    // maker and taker stakes at play; when filled in parts only the matched part of the maker's stake
+    pub fn matched_stakes(&self) -> (u64, u64) {
+        if self.filled_amount == 0 {
+            return (self.bet_amount, self.taker_amount);
+        }
+
+        let matched_stake = self.bet_amount as u128 * self.filled_amount as u128 / self.taker_amount as u128;
+        (matched_stake as u64, self.filled_amount)
+    }
+
+    // what the maker gets back when the settlement is voided
+    fn maker_refundable_stake(&self) -> u64 {
+        if self.unfilled_refunded {
+            self.matched_stakes().0
+        }
+        else {
+            self.bet_amount
+        }
+    }
+
+    // the previous observation's price is weighted by the time it stood
+    fn record_observation(&mut self, price: i64, expo: i32, timestamp: i64) -> Result<()> {
+        if self.twap_last_observed > 0 {
+            if expo != self.twap_expo {
+                return Err(Errors::PriceExponentChanged.into());
+            }
+            let elapsed = timestamp - self.twap_last_observed;
+            self.twap_cumulative = self.twap_cumulative
+                .checked_add(self.twap_last_price as i128 * elapsed as i128)
+                .ok_or(Errors::Overflow)?;
+            self.twap_duration = self.twap_duration.checked_add(elapsed).ok_or(Errors::Overflow)?;
+        }
+
+        self.twap_last_price = price;
+        self.twap_last_observed = timestamp;
+        self.twap_expo = expo;
+
+        Ok(())
+    }
+
+    // the last observation stands until expiry; None when too little of the window was observed
+    fn twap_price(&self) -> Result<Option<pyth_sdk::Price>> {
+        if self.twap_last_observed == 0 {
+            return Ok(None);
+        }
+
+        let elapsed = self.expiry - self.twap_last_observed;
+        let cumulative = self.twap_cumulative
+            .checked_add(self.twap_last_price as i128 * elapsed as i128)
+            .ok_or(Errors::Overflow)?;
+        let duration = self.twap_duration.checked_add(elapsed).ok_or(Errors::Overflow)?;
+        if duration < MIN_TWAP_DURATION {
+            return Ok(None);
+        }
+
+        Ok(Some(pyth_sdk::Price {
+            price: (cumulative / duration as i128) as i64,
+            conf: 0,
+            expo: self.twap_expo,
+            publish_time: self.expiry,
+        }))
+    }
 }
+
 #[account]
+#[derive(InitSpace)]
 pub struct DepositBaseAdmin {
     pub admin_auth: Pubkey,
     pub admin_auth_bump: u8,
     pub admin_sol_vault_bump: Option<u8>,
     pub is_initialized: bool,
+    pub resolver: Option<Pubkey>,
 }
 
-impl DepositBaseAdmin {
-    const LEN: usize = DISCRIMINATOR_LENGTH +
-                       PUBLIC_KEY_LENGTH +
-                       (1 + 2 * U8_LENGTH) +
-                       BOOL_LENGTH;
+#[account]
+#[derive(InitSpace)]
+pub struct Referrer {
+    pub referrer_auth: Pubkey,
+    pub bump: u8,
+    pub accrued_fees: u64,
+    pub claimed_fees: u64,
 }
 
-//Calculate the space for the enum. I just gave it value 1
-#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone)]
+// a taker's part of a binary option filled by several takers
+#[account]
+#[derive(InitSpace)]
+pub struct Fill {
+    pub deposit_account: Pubkey,
+    pub taker_auth: Pubkey,
+    pub amount: u64,
+    pub bump: u8,
+}
+
+// removes an option from the order book it was listed in, which must then be passed in
+fn unlist(deposit_account: &Account<BinaryOption>, order_book: &Option<AccountLoader<OrderBook>>) -> Result<()> {
+    let listed_in = match deposit_account.order_book {
+        Some(listed_in) => listed_in,
+        None => return Ok(()),
+    };
+
+    match order_book {
+        Some(order_book) if order_book.key() == listed_in => {
+            // expired offers may already have been pruned by a keeper
+            let mut order_book = order_book.load_mut()?;
+            if order_book.contains(&deposit_account.key()) {
+                order_book.remove(&deposit_account.key())?;
+            }
+            Ok(())
+        }
+        _ => Err(Errors::OrderBookMissing.into()),
+    }
+}
+
+// lists the part of an option that is left to fill in place of its previous listing
+fn relist(
+    deposit_account: &Account<BinaryOption>,
+    order_book: &Option<AccountLoader<OrderBook>>,
+    bet_amount: u64,
+    taker_amount: u64,
+) -> Result<()> {
+    unlist(deposit_account, order_book)?;
+
+    // a remainder too small to list is left to be filled off the book
+    if bet_amount < MIN_LISTING_AMOUNT {
+        return Ok(());
+    }
+    if let Some(order_book) = order_book.as_ref().filter(|_| deposit_account.order_book.is_some()) {
+        let mut order_book = order_book.load_mut()?;
+        let maker_position = match deposit_account.first_participant {
+            ParticipantPosition::Long => POSITION_LONG,
+            _ => POSITION_SHORT,
+        };
+        order_book.insert(OrderBookEntry {
+            option: deposit_account.key(),
+            strike_price: deposit_account.strike_price,
+            expiry: deposit_account.expiry,
+            bet_amount,
+            taker_amount,
+            maker_position,
+            ..OrderBookEntry::default()
+        })?;
+    }
+
+    Ok(())
+}
+
+// reallocs an account of type T to new_len, topping up rent from the payer. The bytes added are zeroed,
+// which decode as the default value (None, 0, false, first enum variant) of every appended field.
+fn grow_account<'info, T: AccountDeserialize + Discriminator>(
+    account: &UncheckedAccount<'info>,
+    payer: &Signer<'info>,
+    sys_program: &Program<'info, System>,
+    new_len: usize,
+) -> Result<()> {
+    {
+        let data = account.try_borrow_data()?;
+        if data.len() < DISCRIMINATOR_LENGTH || data[..DISCRIMINATOR_LENGTH] != T::discriminator() {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        if data.len() >= new_len {
+            return Err(Errors::AccountAlreadyMigrated.into());
+        }
+    }
+
+    let lamports_needed = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(account.lamports());
+    if lamports_needed > 0 {
+        let cpi_accounts = system_program::Transfer {
+            from: payer.to_account_info(),
+            to: account.to_account_info(),
+        };
+
+        let cpi = CpiContext::new(sys_program.to_account_info(), cpi_accounts);
+
+        system_program::transfer(cpi, lamports_needed)?;
+    }
+
+    account.to_account_info().realloc(new_len, true)?;
+
+    // the upgraded account must decode with the current layout
+    let data = account.try_borrow_data()?;
+    T::try_deserialize(&mut &data[..])?;
+
+    Ok(())
+}
+
+// a referrer is only recorded once it is registered, otherwise its fees could never be credited and
+// the option could not settle
+fn check_referrer(referrer: Option<Pubkey>, referrer_account: &Option<Account<Referrer>>) -> Result<()> {
+    if let Some(referrer_auth) = referrer {
+        let referrer_account = referrer_account.as_ref().ok_or(Errors::ReferrerAccountMissing)?;
+        if referrer_account.referrer_auth != referrer_auth {
+            return Err(Errors::InvalidReferrer.into());
+        }
+    }
+
+    Ok(())
+}
+
+// protocol fee taken from the pot of a settled option, capped at MAX_FEE_BPS of the pot
+fn settlement_fee(pot: u64, fee_bps: u16) -> Result<u64> {
+    let fee = pot
+        .checked_mul((fee_bps as u64).min(MAX_FEE_BPS))
+        .ok_or(Errors::Overflow)?
+        / MAX_BPS;
+
+    Ok(fee)
+}
+
+// share of the fees paid by one participant that is owed to their referrer
+fn referral_fee(bet_fees: u64, referral_fee_bps: u16) -> Result<u64> {
+    let participant_fees = bet_fees / 2;
+    let fee = participant_fees
+        .checked_mul(referral_fee_bps as u64)
+        .ok_or(Errors::Overflow)?
+        / MAX_BPS;
+
+    Ok(fee)
+}
+
+// moves the fees of a settled option out of its vault: the referral share of each referred participant
+// is credited to their referrer and the rest goes to the house
+#[allow(clippy::too_many_arguments)]
+fn distribute_fees<'info>(
+    deposit_account: &BinaryOption,
+    pda_auth: &UncheckedAccount<'info>,
+    sol_vault: &SystemAccount<'info>,
+    admin_sol_vault: &SystemAccount<'info>,
+    maker_referrer: &mut Option<Account<'info, Referrer>>,
+    taker_referrer: &mut Option<Account<'info, Referrer>>,
+    sys_program: &Program<'info, System>,
+    referral_fee_bps: u16,
+) -> Result<()> {
+    let bet_fees = deposit_account.bet_fees;
+
+    // each participant carries half of the fees, so a referrer earns their share of that half
+    let maker_referral_fee = match deposit_account.maker_referrer {
+        Some(_) => referral_fee(bet_fees, referral_fee_bps)?,
+        None => 0,
+    };
+    let taker_referral_fee = match deposit_account.taker_referrer {
+        Some(_) => referral_fee(bet_fees, referral_fee_bps)?,
+        None => 0,
+    };
+    let house_fee = bet_fees
+        .checked_sub(maker_referral_fee)
+        .and_then(|fee| fee.checked_sub(taker_referral_fee))
+        .ok_or(Errors::Overflow)?;
+
+    let seeds = &[
+        b"sol_vault",
+        pda_auth.to_account_info().key.as_ref(),
+        &[deposit_account.sol_vault_bump.unwrap()],
+    ];
+
+    let signer = &[&seeds[..]];
+
+    // step 1: deposit (bet_fees less referral fees) sol to admin vault
+    let cpi_accounts = system_program::Transfer {
+        from: sol_vault.to_account_info(),
+        to: admin_sol_vault.to_account_info(),
+    };
+
+    let cpi = CpiContext::new_with_signer(sys_program.to_account_info(), cpi_accounts, signer);
+
+    system_program::transfer(cpi, house_fee)?;
+
+    // step 2: credit the referral fees to the referrers of both participants
+    if maker_referral_fee > 0 {
+        let referrer = maker_referrer.as_mut().ok_or(Errors::ReferrerAccountMissing)?;
+        if Some(referrer.referrer_auth) != deposit_account.maker_referrer {
+            return Err(Errors::InvalidReferrer.into());
+        }
+        referrer.accrued_fees = referrer.accrued_fees.checked_add(maker_referral_fee).ok_or(Errors::Overflow)?;
+
+        let cpi_accounts = system_program::Transfer {
+            from: sol_vault.to_account_info(),
+            to: referrer.to_account_info(),
+        };
+
+        let cpi = CpiContext::new_with_signer(sys_program.to_account_info(), cpi_accounts, signer);
+
+        system_program::transfer(cpi, maker_referral_fee)?;
+    }
+
+    if taker_referral_fee > 0 {
+        let referrer = taker_referrer.as_mut().ok_or(Errors::ReferrerAccountMissing)?;
+        if Some(referrer.referrer_auth) != deposit_account.taker_referrer {
+            return Err(Errors::InvalidReferrer.into());
+        }
+        referrer.accrued_fees = referrer.accrued_fees.checked_add(taker_referral_fee).ok_or(Errors::Overflow)?;
+
+        let cpi_accounts = system_program::Transfer {
+            from: sol_vault.to_account_info(),
+            to: referrer.to_account_info(),
+        };
+
+        let cpi = CpiContext::new_with_signer(sys_program.to_account_info(), cpi_accounts, signer);
+
+        system_program::transfer(cpi, taker_referral_fee)?;
+    }
+
+    Ok(())
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Copy, Clone)]
 pub enum ParticipantPosition {
     Long,
     Short,
     Unknown,
 }
+// price an option is settled against
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SettlementMode {
+    Spot, // latest aggregate price
+    Ema, // Pyth exponential moving average
+    Twap, // time-weighted average of observe_price calls before expiry
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone)]
 pub enum Participants {
     First,
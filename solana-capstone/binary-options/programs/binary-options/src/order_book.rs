@@ -0,0 +1,120 @@
+use std::cmp::Ordering;
+use anchor_lang::prelude::*;
+
+use crate::Errors;
+
+pub const ORDER_BOOK_CAPACITY: usize = 64; // open options listed per market
+pub const EXPIRY_BUCKET: i64 = 60 * 60; // options expiring within the same hour are matched together
+pub const MIN_LISTING_AMOUNT: u64 = 100_000_000; // 0.1 SOL, keeps dust offers from taking up the book
+
+pub const POSITION_LONG: u8 = 0;
+pub const POSITION_SHORT: u8 = 1;
+
+// Open options of one market (config), kept sorted by strike, expiry bucket and then price so a
+// taker finds every offer for a strike and expiry next to each other with the best one first.
+#[account(zero_copy)]
+pub struct OrderBook {
+    pub config: Pubkey,
+    pub len: u64,
+    pub entries: [OrderBookEntry; ORDER_BOOK_CAPACITY],
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct OrderBookEntry {
+    pub option: Pubkey,
+    pub strike_price: u64,
+    pub expiry: i64, // zero when the option can be settled as soon as it is accepted
+    pub bet_amount: u64, // staked by the maker
+    pub taker_amount: u64, // asked from the taker
+    pub maker_position: u8, // POSITION_LONG or POSITION_SHORT, the taker takes the other side
+    pub padding: [u8; 7],
+}
+
+impl OrderBookEntry {
+    pub fn expiry_bucket(&self) -> i64 {
+        self.expiry / EXPIRY_BUCKET
+    }
+
+    // a taker prefers the offer that asks the least for what it pays out
+    fn cmp_price(&self, other: &Self) -> Ordering {
+        let price = self.taker_amount as u128 * other.bet_amount as u128;
+        let other_price = other.taker_amount as u128 * self.bet_amount as u128;
+        price.cmp(&other_price)
+    }
+
+    fn cmp_book(&self, other: &Self) -> Ordering {
+        self.strike_price
+            .cmp(&other.strike_price)
+            .then(self.expiry_bucket().cmp(&other.expiry_bucket()))
+            .then(self.cmp_price(other))
+    }
+}
+
+impl OrderBook {
+    pub fn entries(&self) -> &[OrderBookEntry] {
+        &self.entries[..self.len as usize]
+    }
+
+    pub fn insert(&mut self, entry: OrderBookEntry) -> Result<()> {
+        let len = self.len as usize;
+        if len == ORDER_BOOK_CAPACITY {
+            return Err(Errors::OrderBookFull.into());
+        }
+
+        // offers with the same price keep their listing order
+        let index = self.entries[..len]
+            .iter()
+            .position(|listed| entry.cmp_book(listed) == Ordering::Less)
+            .unwrap_or(len);
+        self.entries.copy_within(index..len, index + 1);
+        self.entries[index] = entry;
+        self.len += 1;
+
+        Ok(())
+    }
+
+    pub fn contains(&self, option: &Pubkey) -> bool {
+        self.entries().iter().any(|listed| listed.option == *option)
+    }
+
+    // drops every entry matching evict, returning how many were dropped
+    pub fn prune(&mut self, mut evict: impl FnMut(&OrderBookEntry) -> bool) -> usize {
+        let len = self.len as usize;
+        let mut kept = 0;
+        for index in 0..len {
+            if !evict(&self.entries[index]) {
+                self.entries[kept] = self.entries[index];
+                kept += 1;
+            }
+        }
+        for entry in &mut self.entries[kept..len] {
+            *entry = OrderBookEntry::default();
+        }
+        self.len = kept as u64;
+
+        len - kept
+    }
+
+    pub fn remove(&mut self, option: &Pubkey) -> Result<()> {
+        let len = self.len as usize;
+        let index = self.entries[..len]
+            .iter()
+            .position(|listed| listed.option == *option)
+            .ok_or(Errors::OptionNotListed)?;
+        self.entries.copy_within(index + 1..len, index);
+        self.entries[len - 1] = OrderBookEntry::default();
+        self.len -= 1;
+
+        Ok(())
+    }
+
+    // best priced offer a taker can fill for a strike and expiry, taking the side opposite the maker
+    pub fn best_offer(&self, strike_price: u64, expiry: i64, maker_position: u8) -> Option<&OrderBookEntry> {
+        self.entries().iter().find(|listed| {
+            listed.strike_price == strike_price &&
+                listed.expiry_bucket() == expiry / EXPIRY_BUCKET &&
+                listed.maker_position == maker_position
+        })
+    }
+}
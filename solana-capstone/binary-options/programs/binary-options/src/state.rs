@@ -3,11 +3,20 @@ use std::str::FromStr;
 use anchor_lang::prelude::*;
 use pyth_sdk_solana::state::load_price_account;
 
-use crate::Errors;
+use crate::{Errors, SettlementMode};
 
 #[account]
+#[derive(InitSpace)]
 pub struct AdminConfig {
     pub price_feed_id: Pubkey,
+    // protocol fee taken from the pot of every settled option, in basis points
+    pub fee_bps: u16,
+    // share of each participant's protocol fee credited to their referrer, in basis points
+    pub referral_fee_bps: u16,
+    // seconds a settlement can be disputed before its payout becomes claimable
+    pub dispute_window: i64,
+    // price every option of the market is settled against
+    pub settlement_mode: SettlementMode,
 }
 
 #[derive(Clone)]
@@ -24,7 +33,7 @@ impl anchor_lang::Owner for PriceFeed {
 impl anchor_lang::AccountDeserialize for PriceFeed {
     fn try_deserialize_unchecked(data: &mut &[u8]) -> Result<Self>{
         let account = load_price_account(data)
-            .map_err(|_x| error!(Errors::PythError))?;
+            .map_err(|_x| error!(Errors::InvalidPriceAccount))?;
 
         // Use a dummy key since the key field will be removed from the SDK
         let zeros: [u8; 32] = [0; 32];
@@ -0,0 +1,1353 @@
+// Integration tests running the program in solana-program-test with synthetic Pyth price accounts,
+// so the whole option lifecycle can be exercised offline.
+use anchor_lang::{AccountDeserialize, AccountSerialize, InstructionData, Owner, Space, ToAccountMetas};
+use binary_options::error::Errors;
+use binary_options::instruction::CreateBinaryOptions;
+use binary_options::order_book::{OrderBook, MIN_LISTING_AMOUNT, POSITION_LONG};
+use binary_options::state::{AdminConfig, PriceFeed};
+use binary_options::{BinaryOption, DepositBaseAdmin, Fill, ParticipantPosition, SettlementMode};
+use pyth_sdk_solana::state::{AccountType, PriceAccount, PriceStatus, MAGIC, VERSION_2};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+const BET_AMOUNT: u64 = 2 * LAMPORTS_PER_SOL;
+const TAKER_AMOUNT: u64 = LAMPORTS_PER_SOL;
+const FEE_BPS: u16 = 500;
+const BET_FEES: u64 = (BET_AMOUNT + TAKER_AMOUNT) * FEE_BPS as u64 / 10_000;
+const STRIKE_PRICE: u64 = 25;
+// 25 USD with the exponent used by the SOL/USD feed
+const PYTH_PRICE: i64 = 25 * 100_000_000;
+const PYTH_EXPO: i32 = -8;
+
+struct TestEnv {
+    context: ProgramTestContext,
+    config: Pubkey,
+    price_feed: Pubkey,
+    admin_deposit_account: Pubkey,
+    admin_pda_auth: Pubkey,
+    admin_sol_vault: Pubkey,
+    admin_auth: Keypair,
+    maker: Keypair,
+    taker: Keypair,
+}
+
+struct OptionAccounts {
+    deposit_account: Keypair,
+    pda_auth: Pubkey,
+    sol_vault: Pubkey,
+    order_book: Option<Pubkey>,
+}
+
+impl OptionAccounts {
+    fn new() -> Self {
+        let deposit_account = Keypair::new();
+        let (pda_auth, _) = Pubkey::find_program_address(
+            &[b"auth", deposit_account.pubkey().as_ref()],
+            &binary_options::id(),
+        );
+        let (sol_vault, _) =
+            Pubkey::find_program_address(&[b"sol_vault", pda_auth.as_ref()], &binary_options::id());
+
+        OptionAccounts {
+            deposit_account,
+            pda_auth,
+            sol_vault,
+            order_book: None,
+        }
+    }
+
+    fn listed(order_book: Pubkey) -> Self {
+        OptionAccounts {
+            order_book: Some(order_book),
+            ..OptionAccounts::new()
+        }
+    }
+}
+
+// the option a rolled over payout is restaked into lives at a PDA of the previous round
+struct NextRoundAccounts {
+    deposit_account: Pubkey,
+    pda_auth: Pubkey,
+    sol_vault: Pubkey,
+}
+
+impl NextRoundAccounts {
+    fn new(previous: &OptionAccounts) -> Self {
+        let (deposit_account, _) = Pubkey::find_program_address(
+            &[b"next_round", previous.deposit_account.pubkey().as_ref()],
+            &binary_options::id(),
+        );
+        let (pda_auth, _) =
+            Pubkey::find_program_address(&[b"auth", deposit_account.as_ref()], &binary_options::id());
+        let (sol_vault, _) =
+            Pubkey::find_program_address(&[b"sol_vault", pda_auth.as_ref()], &binary_options::id());
+
+        NextRoundAccounts {
+            deposit_account,
+            pda_auth,
+            sol_vault,
+        }
+    }
+}
+
+// serializes an anchor account (discriminator included), padded so fields can grow in place
+fn program_account<T: AccountSerialize>(account: &T, len: usize) -> Account {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    data.resize(data.len().max(len), 0);
+
+    Account {
+        lamports: LAMPORTS_PER_SOL,
+        data,
+        owner: binary_options::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn clone_keypair(keypair: &Keypair) -> Keypair {
+    Keypair::from_bytes(&keypair.to_bytes()).unwrap()
+}
+
+fn referrer_account(referrer_auth: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"referrer", referrer_auth.as_ref()], &binary_options::id()).0
+}
+
+fn system_account(lamports: u64) -> Account {
+    Account::new(lamports, 0, &system_program::id())
+}
+
+// builds a Pyth v2 price account the same way the oracle lays it out on chain
+fn pyth_price_account(price: i64, expo: i32, conf: u64, publish_time: i64) -> Account {
+    let mut price_account: PriceAccount = bytemuck::Zeroable::zeroed();
+    price_account.magic = MAGIC;
+    price_account.ver = VERSION_2;
+    price_account.atype = AccountType::Price as u32;
+    price_account.expo = expo;
+    price_account.timestamp = publish_time;
+    price_account.agg.price = price;
+    price_account.agg.conf = conf;
+    price_account.agg.status = PriceStatus::Trading;
+    price_account.ema_price.val = price;
+    price_account.ema_conf.val = conf as i64;
+
+    Account {
+        lamports: LAMPORTS_PER_SOL,
+        data: bytemuck::bytes_of(&price_account).to_vec(),
+        owner: PriceFeed::owner(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+// a long option on STRIKE_PRICE without expiry
+fn create_args() -> CreateBinaryOptions {
+    CreateBinaryOptions {
+        bet_description: "A:SOL~P:LONG~S:$25~B:2SOL~T:1SOL".to_string(),
+        bet_amount: BET_AMOUNT,
+        strike_price: STRIKE_PRICE,
+        taker_amount: TAKER_AMOUNT,
+        participant_position: ParticipantPosition::Long,
+        referrer: None,
+        expiry: 0,
+    }
+}
+
+async fn setup() -> TestEnv {
+    setup_with(SettlementMode::Spot, 0).await
+}
+
+async fn setup_with_mode(settlement_mode: SettlementMode) -> TestEnv {
+    setup_with(settlement_mode, 0).await
+}
+
+async fn setup_with_dispute_window(dispute_window: i64) -> TestEnv {
+    setup_with(SettlementMode::Spot, dispute_window).await
+}
+
+// the settlement mode and dispute window are market settings, so each needs its own config
+async fn setup_with(settlement_mode: SettlementMode, dispute_window: i64) -> TestEnv {
+    let mut program_test = ProgramTest::new(
+        "binary_options",
+        binary_options::id(),
+        processor!(binary_options::entry),
+    );
+
+    let config = Pubkey::new_unique();
+    let price_feed = Pubkey::new_unique();
+    let admin_auth = Keypair::new();
+    let admin_deposit_account = Pubkey::new_unique();
+    let (admin_pda_auth, admin_auth_bump) = Pubkey::find_program_address(
+        &[b"admin_auth", admin_deposit_account.as_ref()],
+        &binary_options::id(),
+    );
+    let (admin_sol_vault, admin_sol_vault_bump) = Pubkey::find_program_address(
+        &[b"admin_sol_vault", admin_pda_auth.as_ref()],
+        &binary_options::id(),
+    );
+    let maker = Keypair::new();
+    let taker = Keypair::new();
+
+    // initialize requires the program keypair to sign, so the admin state is injected directly
+    program_test.add_account(
+        config,
+        program_account(
+            &AdminConfig {
+                price_feed_id: price_feed,
+                fee_bps: FEE_BPS,
+                referral_fee_bps: 0,
+                dispute_window,
+                settlement_mode,
+            },
+            0,
+        ),
+    );
+    program_test.add_account(
+        admin_deposit_account,
+        program_account(
+            &DepositBaseAdmin {
+                admin_auth: admin_auth.pubkey(),
+                admin_auth_bump,
+                admin_sol_vault_bump: Some(admin_sol_vault_bump),
+                is_initialized: true,
+                resolver: None,
+            },
+            128,
+        ),
+    );
+    program_test.add_account(admin_auth.pubkey(), system_account(10 * LAMPORTS_PER_SOL));
+    program_test.add_account(maker.pubkey(), system_account(100 * LAMPORTS_PER_SOL));
+    program_test.add_account(taker.pubkey(), system_account(100 * LAMPORTS_PER_SOL));
+
+    let context = program_test.start_with_context().await;
+
+    TestEnv {
+        context,
+        config,
+        price_feed,
+        admin_deposit_account,
+        admin_pda_auth,
+        admin_sol_vault,
+        admin_auth,
+        maker,
+        taker,
+    }
+}
+
+impl TestEnv {
+    async fn process(
+        &mut self,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let blockhash = self.context.banks_client.get_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    async fn fund(&mut self, address: Pubkey, lamports: u64) {
+        let instruction =
+            solana_sdk::system_instruction::transfer(&self.context.payer.pubkey(), &address, lamports);
+        self.process(instruction, &[]).await.unwrap();
+    }
+
+    async fn now(&mut self) -> i64 {
+        let clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp
+    }
+
+    // moves to a new slot (and blockhash) before shifting the cluster time forward
+    async fn advance_clock(&mut self, seconds: i64) {
+        let clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        self.context.warp_to_slot(clock.slot + 2).unwrap();
+
+        let mut clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp += seconds;
+        self.context.set_sysvar(&clock);
+    }
+
+    async fn set_price(&mut self, price_feed: Pubkey, price: i64, publish_time: i64) {
+        self.context.set_account(
+            &price_feed,
+            &pyth_price_account(price, PYTH_EXPO, 1_000, publish_time).into(),
+        );
+    }
+
+    async fn balance(&mut self, address: Pubkey) -> u64 {
+        self.context.banks_client.get_balance(address).await.unwrap()
+    }
+
+    async fn binary_option(&mut self, address: Pubkey) -> BinaryOption {
+        let account = self
+            .context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .unwrap();
+        BinaryOption::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    fn create_ix(&self, option: &OptionAccounts, expiry: i64) -> Instruction {
+        self.create_ix_with(
+            option,
+            CreateBinaryOptions {
+                expiry,
+                ..create_args()
+            },
+        )
+    }
+
+    fn create_ix_with(&self, option: &OptionAccounts, args: CreateBinaryOptions) -> Instruction {
+        self.referred_create_ix(option, args, None)
+    }
+
+    fn referred_create_ix(
+        &self,
+        option: &OptionAccounts,
+        args: CreateBinaryOptions,
+        referrer: Option<Pubkey>,
+    ) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::CreateBinaryOptions {
+                config: self.config,
+                deposit_account: option.deposit_account.pubkey(),
+                pda_auth: option.pda_auth,
+                sol_vault: option.sol_vault,
+                deposit_auth: self.maker.pubkey(),
+                admin_deposit_account: self.admin_deposit_account,
+                order_book: option.order_book,
+                referrer,
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: args.data(),
+        }
+    }
+
+    fn accept_ix(&self, option: &OptionAccounts) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::AcceptBinaryOptions {
+                admin_deposit_account: self.admin_deposit_account,
+                admin_pda_auth: self.admin_pda_auth,
+                admin_sol_vault: self.admin_sol_vault,
+                deposit_account: option.deposit_account.pubkey(),
+                pda_auth: option.pda_auth,
+                sol_vault: option.sol_vault,
+                deposit_auth: self.taker.pubkey(),
+                order_book: option.order_book,
+                referrer: None,
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::AcceptBinaryOptions {
+                amount: TAKER_AMOUNT,
+                participant_position: ParticipantPosition::Short,
+                referrer: None,
+            }
+            .data(),
+        }
+    }
+
+    fn process_prediction_ix(&self, option: &OptionAccounts, price_feed: Pubkey) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::ProcessPrediction {
+                config: self.config,
+                pyth_price_feed_account: price_feed,
+                deposit_account: option.deposit_account.pubkey(),
+                pda_auth: option.pda_auth,
+                sol_vault: option.sol_vault,
+                admin_deposit_account: self.admin_deposit_account,
+                admin_pda_auth: self.admin_pda_auth,
+                admin_sol_vault: self.admin_sol_vault,
+                maker_referrer: None,
+                taker_referrer: None,
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::ProcessPrediction {}.data(),
+        }
+    }
+
+    fn fill_ix(&self, option: &OptionAccounts, taker_auth: Pubkey, amount: u64) -> Instruction {
+        let (fill, _) = Pubkey::find_program_address(
+            &[b"fill", option.deposit_account.pubkey().as_ref(), taker_auth.as_ref()],
+            &binary_options::id(),
+        );
+
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::FillBinaryOptions {
+                deposit_account: option.deposit_account.pubkey(),
+                pda_auth: option.pda_auth,
+                sol_vault: option.sol_vault,
+                fill,
+                taker_auth,
+                order_book: option.order_book,
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::FillBinaryOptions { amount }.data(),
+        }
+    }
+
+    fn claim_fill_ix(&self, option: &OptionAccounts, taker_auth: Pubkey) -> Instruction {
+        let (fill, _) = Pubkey::find_program_address(
+            &[b"fill", option.deposit_account.pubkey().as_ref(), taker_auth.as_ref()],
+            &binary_options::id(),
+        );
+
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::ClaimFill {
+                deposit_account: option.deposit_account.pubkey(),
+                pda_auth: option.pda_auth,
+                sol_vault: option.sol_vault,
+                fill,
+                taker_auth,
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::ClaimFill {}.data(),
+        }
+    }
+
+    fn refund_unfilled_ix(&self, option: &OptionAccounts) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::RefundUnfilled {
+                deposit_account: option.deposit_account.pubkey(),
+                pda_auth: option.pda_auth,
+                sol_vault: option.sol_vault,
+                deposit_auth: self.maker.pubkey(),
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::RefundUnfilled {}.data(),
+        }
+    }
+
+    fn register_referrer_ix(&self, referrer_auth: Pubkey) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::RegisterReferrer {
+                referrer: referrer_account(referrer_auth),
+                referrer_auth,
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::RegisterReferrer {}.data(),
+        }
+    }
+
+    fn init_order_book_ix(&self, order_book: Pubkey) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::InitOrderBook {
+                config: self.config,
+                order_book,
+                payer: self.context.payer.pubkey(),
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::InitOrderBook {}.data(),
+        }
+    }
+
+    fn prune_order_book_ix(&self, order_book: Pubkey, options: &[Pubkey]) -> Instruction {
+        let mut accounts = binary_options::accounts::PruneOrderBook { order_book }.to_account_metas(None);
+        accounts.extend(options.iter().map(|option| AccountMeta::new_readonly(*option, false)));
+
+        Instruction {
+            program_id: binary_options::id(),
+            accounts,
+            data: binary_options::instruction::PruneOrderBook {}.data(),
+        }
+    }
+
+    async fn order_book(&mut self, address: Pubkey) -> OrderBook {
+        let account = self
+            .context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .unwrap();
+        bytemuck::pod_read_unaligned(&account.data[8..8 + std::mem::size_of::<OrderBook>()])
+    }
+
+    fn set_rollover_ix(&self, option: &OptionAccounts, participant: Pubkey, enabled: bool) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::SetRollover {
+                deposit_account: option.deposit_account.pubkey(),
+                participant,
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::SetRollover { enabled }.data(),
+        }
+    }
+
+    fn rollover_round_ix(&self, option: &OptionAccounts, next_round: &NextRoundAccounts) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::RolloverRound {
+                deposit_account: option.deposit_account.pubkey(),
+                pda_auth: option.pda_auth,
+                sol_vault: option.sol_vault,
+                next_round: next_round.deposit_account,
+                next_pda_auth: next_round.pda_auth,
+                next_sol_vault: next_round.sol_vault,
+                order_book: option.order_book,
+                payer: self.context.payer.pubkey(),
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::RolloverRound {}.data(),
+        }
+    }
+
+    fn observe_price_ix(&self, option: &OptionAccounts) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::ObservePrice {
+                config: self.config,
+                pyth_price_feed_account: self.price_feed,
+                deposit_account: option.deposit_account.pubkey(),
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::ObservePrice {}.data(),
+        }
+    }
+
+    fn finalize_settlement_ix(&self, option: &OptionAccounts) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::FinalizeSettlement {
+                config: self.config,
+                deposit_account: option.deposit_account.pubkey(),
+                pda_auth: option.pda_auth,
+                sol_vault: option.sol_vault,
+                admin_deposit_account: self.admin_deposit_account,
+                admin_pda_auth: self.admin_pda_auth,
+                admin_sol_vault: self.admin_sol_vault,
+                maker_referrer: None,
+                taker_referrer: None,
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::FinalizeSettlement {}.data(),
+        }
+    }
+
+    fn void_settlement_ix(&self, option: &OptionAccounts, resolver_auth: Pubkey, refund: bool) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::VoidSettlement {
+                deposit_account: option.deposit_account.pubkey(),
+                admin_deposit_account: self.admin_deposit_account,
+                resolver_auth,
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::VoidSettlement { refund }.data(),
+        }
+    }
+
+    fn withdraw_ix(&self, option: &OptionAccounts, participant: Pubkey, amount: u64) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::WithdrawParticipantFunds {
+                deposit_account: option.deposit_account.pubkey(),
+                pda_auth: option.pda_auth,
+                sol_vault: option.sol_vault,
+                deposit_auth: participant,
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::WithdrawParticipantFunds { amount }.data(),
+        }
+    }
+
+    fn cancel_ix(&self, option: &OptionAccounts) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::CancelBinaryOptions {
+                deposit_account: option.deposit_account.pubkey(),
+                pda_auth: option.pda_auth,
+                sol_vault: option.sol_vault,
+                deposit_auth: self.maker.pubkey(),
+                order_book: option.order_book,
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::CancelBinaryOptions {}.data(),
+        }
+    }
+
+    fn migrate_binary_option_ix(&self, deposit_account: Pubkey) -> Instruction {
+        Instruction {
+            program_id: binary_options::id(),
+            accounts: binary_options::accounts::MigrateBinaryOption {
+                deposit_account,
+                payer: self.context.payer.pubkey(),
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: binary_options::instruction::MigrateBinaryOption {}.data(),
+        }
+    }
+
+    // creates and accepts an option so it is ready to be settled
+    async fn open_option(&mut self) -> OptionAccounts {
+        self.open_option_with_expiry(0).await
+    }
+
+    async fn open_option_with_expiry(&mut self, expiry: i64) -> OptionAccounts {
+        let option = OptionAccounts::new();
+        let maker = clone_keypair(&self.maker);
+        let taker = clone_keypair(&self.taker);
+
+        let instruction = self.create_ix(&option, expiry);
+        self.process(instruction, &[&option.deposit_account, &maker])
+            .await
+            .unwrap();
+        self.process(self.accept_ix(&option), &[&taker]).await.unwrap();
+
+        option
+    }
+}
+
+fn assert_program_error(result: Result<(), BanksClientError>, error: Errors) {
+    match result.unwrap_err().unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+            assert_eq!(code, u32::from(error))
+        }
+        err => panic!("unexpected transaction error: {:?}", err),
+    }
+}
+
+#[tokio::test]
+async fn initialize_requires_program_signer() {
+    let mut env = setup().await;
+    let impostor = Keypair::new();
+    let config = Keypair::new();
+    let admin_deposit_account = Keypair::new();
+    let (admin_pda_auth, _) = Pubkey::find_program_address(
+        &[b"admin_auth", admin_deposit_account.pubkey().as_ref()],
+        &binary_options::id(),
+    );
+    let (admin_sol_vault, _) = Pubkey::find_program_address(
+        &[b"admin_sol_vault", admin_pda_auth.as_ref()],
+        &binary_options::id(),
+    );
+    let admin_auth = clone_keypair(&env.admin_auth);
+
+    let instruction = Instruction {
+        program_id: binary_options::id(),
+        accounts: binary_options::accounts::Initialize {
+            program: impostor.pubkey(),
+            config: config.pubkey(),
+            admin_deposit_account: admin_deposit_account.pubkey(),
+            admin_pda_auth,
+            admin_sol_vault,
+            admin_auth: admin_auth.pubkey(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: binary_options::instruction::Initialize {
+            config: AdminConfig {
+                price_feed_id: env.price_feed,
+                fee_bps: FEE_BPS,
+                referral_fee_bps: 0,
+                dispute_window: 0,
+                settlement_mode: SettlementMode::Spot,
+            },
+        }
+        .data(),
+    };
+
+    let result = env
+        .process(instruction, &[&impostor, &config, &admin_deposit_account, &admin_auth])
+        .await;
+    assert_program_error(result, Errors::Unauthorized);
+}
+
+#[tokio::test]
+async fn winner_withdraws_payout_after_settlement() {
+    let mut env = setup().await;
+    let option = env.open_option().await;
+
+    let now = env.now().await;
+    env.set_price(env.price_feed, PYTH_PRICE, now).await;
+    let admin_vault_before = env.balance(env.admin_sol_vault).await;
+    env.process(env.process_prediction_ix(&option, env.price_feed), &[])
+        .await
+        .unwrap();
+
+    let settled = env.binary_option(option.deposit_account.pubkey()).await;
+    assert_eq!(settled.actual_price, STRIKE_PRICE);
+    assert_eq!(settled.winner_auth, env.maker.pubkey());
+    assert_eq!(settled.total_payout, BET_AMOUNT + TAKER_AMOUNT - BET_FEES);
+    assert_eq!(env.balance(env.admin_sol_vault).await, admin_vault_before + BET_FEES);
+
+    // the losing participant cannot touch the payout
+    let taker = clone_keypair(&env.taker);
+    let result = env
+        .process(env.withdraw_ix(&option, taker.pubkey(), settled.total_payout), &[&taker])
+        .await;
+    assert_program_error(result, Errors::InvalidWinner);
+
+    let maker = clone_keypair(&env.maker);
+    let maker_before = env.balance(maker.pubkey()).await;
+    env.process(env.withdraw_ix(&option, maker.pubkey(), settled.total_payout), &[&maker])
+        .await
+        .unwrap();
+    assert_eq!(env.balance(maker.pubkey()).await, maker_before + settled.total_payout);
+}
+
+#[tokio::test]
+async fn settlement_fee_is_capped_at_a_tenth_of_the_pot() {
+    let mut env = setup().await;
+    let option = env.open_option().await;
+
+    // a config written with a fee above the cap still cannot take more than 10% of the pot
+    let config = AdminConfig {
+        price_feed_id: env.price_feed,
+        fee_bps: u16::MAX,
+        referral_fee_bps: 0,
+        dispute_window: 0,
+        settlement_mode: SettlementMode::Spot,
+    };
+    env.context.set_account(&env.config, &program_account(&config, 0).into());
+
+    let now = env.now().await;
+    env.set_price(env.price_feed, PYTH_PRICE, now).await;
+    env.process(env.process_prediction_ix(&option, env.price_feed), &[])
+        .await
+        .unwrap();
+
+    let pot = BET_AMOUNT + TAKER_AMOUNT;
+    let settled = env.binary_option(option.deposit_account.pubkey()).await;
+    assert_eq!(settled.bet_fees, pot / 10);
+    assert_eq!(settled.total_payout, pot - pot / 10);
+}
+
+#[tokio::test]
+async fn settlement_rejects_stale_price() {
+    let mut env = setup().await;
+    let option = env.open_option().await;
+
+    let now = env.now().await;
+    env.set_price(env.price_feed, PYTH_PRICE, now - 120).await;
+    let result = env
+        .process(env.process_prediction_ix(&option, env.price_feed), &[])
+        .await;
+    assert_program_error(result, Errors::PythOffline);
+}
+
+#[tokio::test]
+async fn settlement_rejects_wrong_feed() {
+    let mut env = setup().await;
+    let option = env.open_option().await;
+
+    // a valid Pyth account, but not the feed configured by the admin
+    let other_feed = Pubkey::new_unique();
+    let now = env.now().await;
+    env.set_price(other_feed, PYTH_PRICE, now).await;
+    let result = env
+        .process(env.process_prediction_ix(&option, other_feed), &[])
+        .await;
+    assert_program_error(result, Errors::InvalidPriceFeed);
+}
+
+#[tokio::test]
+async fn maker_cancels_open_option() {
+    let mut env = setup().await;
+    let option = OptionAccounts::new();
+    let maker = clone_keypair(&env.maker);
+
+    let maker_before = env.balance(maker.pubkey()).await;
+    env.process(env.create_ix(&option, 0), &[&option.deposit_account, &maker])
+        .await
+        .unwrap();
+    env.process(env.cancel_ix(&option), &[&maker]).await.unwrap();
+
+    // bet amount and the account rent both come back to the maker
+    assert_eq!(env.balance(maker.pubkey()).await, maker_before);
+    assert_eq!(env.balance(option.sol_vault).await, 0);
+}
+
+#[tokio::test]
+async fn accepted_option_cannot_be_cancelled() {
+    let mut env = setup().await;
+    let option = env.open_option().await;
+    let maker = clone_keypair(&env.maker);
+
+    let result = env.process(env.cancel_ix(&option), &[&maker]).await;
+    assert_program_error(result, Errors::InvalidBettingState);
+}
+
+#[tokio::test]
+async fn partially_filled_option_cannot_be_cancelled() {
+    let mut env = setup().await;
+    let option = OptionAccounts::new();
+    let maker = clone_keypair(&env.maker);
+    let taker = clone_keypair(&env.taker);
+    let expiry = env.now().await + 600;
+
+    let instruction = env.create_ix(&option, expiry);
+    env.process(instruction, &[&option.deposit_account, &maker]).await.unwrap();
+    env.process(env.fill_ix(&option, taker.pubkey(), TAKER_AMOUNT / 2), &[&taker])
+        .await
+        .unwrap();
+
+    // the taker's fill stays in the vault until the option settles
+    let result = env.process(env.cancel_ix(&option), &[&maker]).await;
+    assert_program_error(result, Errors::OptionPartiallyFilled);
+    assert_eq!(env.balance(option.sol_vault).await, BET_AMOUNT + TAKER_AMOUNT / 2);
+}
+
+#[tokio::test]
+async fn referrer_must_be_registered_to_be_recorded() {
+    let mut env = setup().await;
+    let maker = clone_keypair(&env.maker);
+    let referrer_auth = Keypair::new();
+    let other_referrer_auth = Keypair::new();
+    env.fund(referrer_auth.pubkey(), LAMPORTS_PER_SOL).await;
+    env.fund(other_referrer_auth.pubkey(), LAMPORTS_PER_SOL).await;
+    let args = || CreateBinaryOptions { referrer: Some(referrer_auth.pubkey()), ..create_args() };
+
+    // an unregistered referrer could never be credited, which would block settlement
+    let option = OptionAccounts::new();
+    let instruction = env.referred_create_ix(&option, args(), None);
+    let result = env.process(instruction, &[&option.deposit_account, &maker]).await;
+    assert_program_error(result, Errors::ReferrerAccountMissing);
+
+    env.process(env.register_referrer_ix(referrer_auth.pubkey()), &[&referrer_auth])
+        .await
+        .unwrap();
+    env.process(env.register_referrer_ix(other_referrer_auth.pubkey()), &[&other_referrer_auth])
+        .await
+        .unwrap();
+
+    let instruction = env.referred_create_ix(&option, args(), Some(referrer_account(other_referrer_auth.pubkey())));
+    let result = env.process(instruction, &[&option.deposit_account, &maker]).await;
+    assert_program_error(result, Errors::InvalidReferrer);
+
+    let instruction = env.referred_create_ix(&option, args(), Some(referrer_account(referrer_auth.pubkey())));
+    env.process(instruction, &[&option.deposit_account, &maker]).await.unwrap();
+    let created = env.binary_option(option.deposit_account.pubkey()).await;
+    assert_eq!(created.maker_referrer, Some(referrer_auth.pubkey()));
+}
+
+#[tokio::test]
+async fn payout_is_locked_during_dispute_window() {
+    let mut env = setup_with_dispute_window(3_600).await;
+    let option = env.open_option().await;
+    let maker = clone_keypair(&env.maker);
+
+    let now = env.now().await;
+    env.set_price(env.price_feed, PYTH_PRICE, now).await;
+    env.process(env.process_prediction_ix(&option, env.price_feed), &[])
+        .await
+        .unwrap();
+
+    let payout = BET_AMOUNT + TAKER_AMOUNT - BET_FEES;
+    let result = env
+        .process(env.withdraw_ix(&option, maker.pubkey(), payout), &[&maker])
+        .await;
+    assert_program_error(result, Errors::DisputeWindowOpen);
+
+    let result = env.process(env.finalize_settlement_ix(&option), &[]).await;
+    assert_program_error(result, Errors::DisputeWindowOpen);
+
+    env.advance_clock(3_600).await;
+    let admin_vault_before = env.balance(env.admin_sol_vault).await;
+    env.process(env.finalize_settlement_ix(&option), &[]).await.unwrap();
+    assert_eq!(env.balance(env.admin_sol_vault).await, admin_vault_before + BET_FEES);
+
+    let maker_before = env.balance(maker.pubkey()).await;
+    env.process(env.withdraw_ix(&option, maker.pubkey(), payout), &[&maker])
+        .await
+        .unwrap();
+    assert_eq!(env.balance(maker.pubkey()).await, maker_before + payout);
+}
+
+#[tokio::test]
+async fn admin_voids_settlement_and_refunds_stakes() {
+    let mut env = setup_with_dispute_window(3_600).await;
+    let option = env.open_option().await;
+    let maker = clone_keypair(&env.maker);
+    let taker = clone_keypair(&env.taker);
+    let admin_auth = clone_keypair(&env.admin_auth);
+
+    let now = env.now().await;
+    env.set_price(env.price_feed, PYTH_PRICE, now).await;
+    env.process(env.process_prediction_ix(&option, env.price_feed), &[])
+        .await
+        .unwrap();
+
+    // only the admin or the designated resolver may void a settlement
+    let result = env
+        .process(env.void_settlement_ix(&option, taker.pubkey(), true), &[&taker])
+        .await;
+    assert_program_error(result, Errors::Unauthorized);
+
+    env.process(env.void_settlement_ix(&option, admin_auth.pubkey(), true), &[&admin_auth])
+        .await
+        .unwrap();
+
+    let maker_before = env.balance(maker.pubkey()).await;
+    let taker_before = env.balance(taker.pubkey()).await;
+    env.process(env.withdraw_ix(&option, maker.pubkey(), BET_AMOUNT), &[&maker])
+        .await
+        .unwrap();
+    env.process(env.withdraw_ix(&option, taker.pubkey(), TAKER_AMOUNT), &[&taker])
+        .await
+        .unwrap();
+    assert_eq!(env.balance(maker.pubkey()).await, maker_before + BET_AMOUNT);
+    assert_eq!(env.balance(taker.pubkey()).await, taker_before + TAKER_AMOUNT);
+}
+
+#[tokio::test]
+async fn legacy_binary_option_is_migrated_in_place() {
+    let mut env = setup().await;
+    let option = env.open_option().await;
+    let address = option.deposit_account.pubkey();
+    let current = env.binary_option(address).await;
+
+    // an option created before the referral and dispute fields were added: referrers (2 * 33),
+    // dispute_window, settled_at, bet_fees (3 * 8) and the two withdrawn flags
+    let mut account = env.context.banks_client.get_account(address).await.unwrap().unwrap();
+    let legacy_len = account.data.len() - (2 * 33 + 3 * 8 + 2);
+    account.data.truncate(legacy_len);
+    account.lamports = env.context.banks_client.get_rent().await.unwrap().minimum_balance(legacy_len);
+    env.context.set_account(&address, &account.into());
+    env.advance_clock(0).await;
+
+    env.process(env.migrate_binary_option_ix(address), &[]).await.unwrap();
+
+    let account = env.context.banks_client.get_account(address).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), 8 + BinaryOption::INIT_SPACE);
+    let rent = env.context.banks_client.get_rent().await.unwrap();
+    assert_eq!(account.lamports, rent.minimum_balance(account.data.len()));
+
+    let migrated = env.binary_option(address).await;
+    assert_eq!(migrated.deposit_auth, current.deposit_auth);
+    assert_eq!(migrated.bet_amount, current.bet_amount);
+    assert_eq!(migrated.betting_state, current.betting_state);
+    assert_eq!(migrated.maker_referrer, None);
+    assert_eq!(migrated.dispute_window, 0);
+
+    env.advance_clock(0).await;
+    let result = env.process(env.migrate_binary_option_ix(address), &[]).await;
+    assert_program_error(result, Errors::AccountAlreadyMigrated);
+}
+
+#[tokio::test]
+async fn twap_settlement_ignores_spot_price_at_expiry() {
+    let mut env = setup_with_mode(SettlementMode::Twap).await;
+    let maker = clone_keypair(&env.maker);
+    let taker = clone_keypair(&env.taker);
+
+    // a TWAP is accumulated over the window before expiry, so options of the market need one
+    let option = OptionAccounts::new();
+    let result = env
+        .process(env.create_ix(&option, 0), &[&option.deposit_account, &maker])
+        .await;
+    assert_program_error(result, Errors::InvalidExpiry);
+
+    let expiry = env.now().await + 600;
+    let option = env.open_option_with_expiry(expiry).await;
+
+    // observations are only accepted in the final minutes before expiry
+    let now = env.now().await;
+    env.set_price(env.price_feed, 20 * 100_000_000, now).await;
+    let result = env.process(env.observe_price_ix(&option), &[]).await;
+    assert_program_error(result, Errors::OutsideObservationWindow);
+
+    env.advance_clock(310).await;
+    let now = env.now().await;
+    env.set_price(env.price_feed, 20 * 100_000_000, now).await;
+    env.process(env.observe_price_ix(&option), &[]).await.unwrap();
+
+    env.advance_clock(140).await;
+    let now = env.now().await;
+    env.set_price(env.price_feed, 30 * 100_000_000, now).await;
+    env.process(env.observe_price_ix(&option), &[]).await.unwrap();
+
+    let result = env.process(env.process_prediction_ix(&option, env.price_feed), &[]).await;
+    assert_program_error(result, Errors::OptionNotExpired);
+
+    // a spike at expiry would decide a spot settlement but barely moves the average
+    env.advance_clock(200).await;
+    let now = env.now().await;
+    env.set_price(env.price_feed, 1_000 * 100_000_000, now).await;
+    env.process(env.process_prediction_ix(&option, env.price_feed), &[])
+        .await
+        .unwrap();
+
+    let settled = env.binary_option(option.deposit_account.pubkey()).await;
+    assert!(settled.actual_price >= 20 && settled.actual_price <= 30);
+
+    // expired options cannot be taken
+    let late = OptionAccounts::new();
+    let expiry = env.now().await + 60;
+    let instruction = env.create_ix(&late, expiry);
+    env.process(instruction, &[&late.deposit_account, &maker]).await.unwrap();
+    env.advance_clock(60).await;
+    let result = env.process(env.accept_ix(&late), &[&taker]).await;
+    assert_program_error(result, Errors::OptionExpired);
+}
+
+#[tokio::test]
+async fn unobserved_twap_option_settles_on_ema_price() {
+    let mut env = setup_with_mode(SettlementMode::Twap).await;
+    let expiry = env.now().await + 600;
+    let option = env.open_option_with_expiry(expiry).await;
+
+    // no keeper observed the window, which must not leave the stakes locked
+    env.advance_clock(600).await;
+    let now = env.now().await;
+    env.set_price(env.price_feed, PYTH_PRICE, now).await;
+    env.process(env.process_prediction_ix(&option, env.price_feed), &[])
+        .await
+        .unwrap();
+
+    let settled = env.binary_option(option.deposit_account.pubkey()).await;
+    assert_eq!(settled.actual_price, STRIKE_PRICE);
+    assert_eq!(settled.winner_auth, env.maker.pubkey());
+}
+
+#[tokio::test]
+async fn unobserved_twap_option_rejects_stale_ema_price() {
+    let mut env = setup_with_mode(SettlementMode::Twap).await;
+    let expiry = env.now().await + 600;
+    let option = env.open_option_with_expiry(expiry).await;
+
+    env.advance_clock(600).await;
+    let now = env.now().await;
+    env.set_price(env.price_feed, PYTH_PRICE, now - 120).await;
+    let result = env
+        .process(env.process_prediction_ix(&option, env.price_feed), &[])
+        .await;
+    assert_program_error(result, Errors::NotEnoughObservations);
+}
+
+#[tokio::test]
+async fn winner_payout_rolls_into_next_round() {
+    let mut env = setup().await;
+    let expiry = env.now().await + 3_600;
+    let option = env.open_option_with_expiry(expiry).await;
+    let next_round = NextRoundAccounts::new(&option);
+    let maker = clone_keypair(&env.maker);
+
+    env.process(env.set_rollover_ix(&option, maker.pubkey(), true), &[&maker])
+        .await
+        .unwrap();
+
+    env.advance_clock(3_600).await;
+    let now = env.now().await;
+    env.set_price(env.price_feed, PYTH_PRICE, now).await;
+    env.process(env.process_prediction_ix(&option, env.price_feed), &[])
+        .await
+        .unwrap();
+
+    // the payout stays put for the crank until the winner opts out
+    let payout = BET_AMOUNT + TAKER_AMOUNT - BET_FEES;
+    let result = env
+        .process(env.withdraw_ix(&option, maker.pubkey(), payout), &[&maker])
+        .await;
+    assert_program_error(result, Errors::RolloverEnabled);
+
+    env.process(env.rollover_round_ix(&option, &next_round), &[])
+        .await
+        .unwrap();
+
+    let previous = env.binary_option(option.deposit_account.pubkey()).await;
+    assert_eq!(previous.next_round, Some(next_round.deposit_account));
+    assert!(previous.maker_withdrawn);
+
+    let next = env.binary_option(next_round.deposit_account).await;
+    assert_eq!(next.deposit_auth, maker.pubkey());
+    assert_eq!(next.betting_state, binary_options::BETTING_STATE_OPEN);
+    assert_eq!(next.bet_amount, payout);
+    assert_eq!(next.taker_amount, TAKER_AMOUNT * payout / BET_AMOUNT);
+    assert_eq!(next.expiry, expiry + 3_600);
+    assert!(next.maker_rollover);
+    assert_eq!(env.balance(next_round.sol_vault).await, payout);
+}
+
+#[tokio::test]
+async fn rollover_skips_missed_rounds_and_relists_next_round() {
+    let mut env = setup().await;
+    let (order_book, _) = Pubkey::find_program_address(
+        &[b"order_book", env.config.as_ref()],
+        &binary_options::id(),
+    );
+    let maker = clone_keypair(&env.maker);
+    let taker = clone_keypair(&env.taker);
+    env.process(env.init_order_book_ix(order_book), &[]).await.unwrap();
+
+    let expiry = env.now().await + 3_600;
+    let option = OptionAccounts::listed(order_book);
+    let instruction = env.create_ix(&option, expiry);
+    env.process(instruction, &[&option.deposit_account, &maker]).await.unwrap();
+    env.process(env.accept_ix(&option), &[&taker]).await.unwrap();
+    env.process(env.set_rollover_ix(&option, maker.pubkey(), true), &[&maker])
+        .await
+        .unwrap();
+
+    env.advance_clock(3_600).await;
+    let now = env.now().await;
+    env.set_price(env.price_feed, PYTH_PRICE, now).await;
+    env.process(env.process_prediction_ix(&option, env.price_feed), &[])
+        .await
+        .unwrap();
+
+    // nobody cranked for a while, so the next round starts after the rounds that were missed
+    env.advance_clock(100 * 3_600 + 1).await;
+    let next_round = NextRoundAccounts::new(&option);
+    let unlisted = OptionAccounts {
+        deposit_account: clone_keypair(&option.deposit_account),
+        pda_auth: option.pda_auth,
+        sol_vault: option.sol_vault,
+        order_book: None,
+    };
+    let result = env.process(env.rollover_round_ix(&unlisted, &next_round), &[]).await;
+    assert_program_error(result, Errors::OrderBookMissing);
+
+    env.process(env.rollover_round_ix(&option, &next_round), &[])
+        .await
+        .unwrap();
+
+    let next = env.binary_option(next_round.deposit_account).await;
+    assert_eq!(next.expiry, expiry + 101 * 3_600);
+    assert_eq!(next.order_book, Some(order_book));
+
+    let book = env.order_book(order_book).await;
+    assert_eq!(book.entries().len(), 1);
+    assert_eq!(book.entries()[0].option, next_round.deposit_account);
+    assert_eq!(book.entries()[0].bet_amount, next.bet_amount);
+}
+
+#[tokio::test]
+async fn order_book_lists_open_options_until_filled_or_cancelled() {
+    let mut env = setup().await;
+    let (order_book, _) = Pubkey::find_program_address(
+        &[b"order_book", env.config.as_ref()],
+        &binary_options::id(),
+    );
+    let maker = clone_keypair(&env.maker);
+    let taker = clone_keypair(&env.taker);
+
+    env.process(env.init_order_book_ix(order_book), &[]).await.unwrap();
+
+    let first = OptionAccounts::listed(order_book);
+    let second = OptionAccounts::listed(order_book);
+    for option in [&first, &second] {
+        let instruction = env.create_ix(option, 0);
+        env.process(instruction, &[&option.deposit_account, &maker]).await.unwrap();
+    }
+
+    // equally priced offers keep their listing order
+    let book = env.order_book(order_book).await;
+    assert_eq!(book.entries().len(), 2);
+    let best = book.best_offer(STRIKE_PRICE, 0, POSITION_LONG).unwrap();
+    assert_eq!(best.option, first.deposit_account.pubkey());
+    assert_eq!(best.taker_amount, TAKER_AMOUNT);
+    assert!(book.best_offer(STRIKE_PRICE + 1, 0, POSITION_LONG).is_none());
+
+    env.process(env.accept_ix(&first), &[&taker]).await.unwrap();
+    let book = env.order_book(order_book).await;
+    assert_eq!(book.entries().len(), 1);
+    assert_eq!(book.entries()[0].option, second.deposit_account.pubkey());
+
+    // a listed option cannot leave the market without being removed from the book
+    let unlisted = OptionAccounts {
+        deposit_account: clone_keypair(&second.deposit_account),
+        pda_auth: second.pda_auth,
+        sol_vault: second.sol_vault,
+        order_book: None,
+    };
+    let result = env.process(env.cancel_ix(&unlisted), &[&maker]).await;
+    assert_program_error(result, Errors::OrderBookMissing);
+
+    env.process(env.cancel_ix(&second), &[&maker]).await.unwrap();
+    let book = env.order_book(order_book).await;
+    assert!(book.entries().is_empty());
+}
+
+#[tokio::test]
+async fn order_book_rejects_dust_and_evicts_expired_offers() {
+    let mut env = setup().await;
+    let (order_book, _) = Pubkey::find_program_address(
+        &[b"order_book", env.config.as_ref()],
+        &binary_options::id(),
+    );
+    let maker = clone_keypair(&env.maker);
+    env.process(env.init_order_book_ix(order_book), &[]).await.unwrap();
+
+    let dust = OptionAccounts::listed(order_book);
+    let instruction = env.create_ix_with(
+        &dust,
+        CreateBinaryOptions { bet_amount: MIN_LISTING_AMOUNT - 1, ..create_args() },
+    );
+    let result = env.process(instruction, &[&dust.deposit_account, &maker]).await;
+    assert_program_error(result, Errors::ListingAmountTooLow);
+
+    let expiry = env.now().await + 600;
+    let expiring = OptionAccounts::listed(order_book);
+    let open = OptionAccounts::listed(order_book);
+    let instruction = env.create_ix(&expiring, expiry);
+    env.process(instruction, &[&expiring.deposit_account, &maker]).await.unwrap();
+    let instruction = env.create_ix(&open, 0);
+    env.process(instruction, &[&open.deposit_account, &maker]).await.unwrap();
+
+    // an open option passed in is kept, only the expired offer is evicted
+    env.advance_clock(600).await;
+    let instruction = env.prune_order_book_ix(order_book, &[open.deposit_account.pubkey()]);
+    env.process(instruction, &[]).await.unwrap();
+    let book = env.order_book(order_book).await;
+    assert_eq!(book.entries().len(), 1);
+    assert_eq!(book.entries()[0].option, open.deposit_account.pubkey());
+
+    // the maker of a pruned offer can still cancel it
+    env.process(env.cancel_ix(&expiring), &[&maker]).await.unwrap();
+    assert_eq!(env.balance(expiring.sol_vault).await, 0);
+}
+
+#[tokio::test]
+async fn partial_fills_share_payout_and_refund_unfilled_stake() {
+    let mut env = setup().await;
+    let expiry = env.now().await + 600;
+    let option = OptionAccounts::new();
+    let maker = clone_keypair(&env.maker);
+    let first_taker = clone_keypair(&env.taker);
+    let second_taker = Keypair::new();
+    env.fund(second_taker.pubkey(), 10 * LAMPORTS_PER_SOL).await;
+
+    let instruction = env.create_ix(&option, expiry);
+    env.process(instruction, &[&option.deposit_account, &maker]).await.unwrap();
+
+    let first_fill = TAKER_AMOUNT * 4 / 10;
+    let second_fill = TAKER_AMOUNT * 3 / 10;
+    env.process(env.fill_ix(&option, first_taker.pubkey(), first_fill), &[&first_taker])
+        .await
+        .unwrap();
+    env.process(env.fill_ix(&option, second_taker.pubkey(), second_fill), &[&second_taker])
+        .await
+        .unwrap();
+
+    let result = env
+        .process(env.fill_ix(&option, maker.pubkey(), TAKER_AMOUNT), &[&maker])
+        .await;
+    assert_program_error(result, Errors::PredictionDisAllowed);
+
+    // the rest can no longer be taken by a single taker
+    let result = env.process(env.accept_ix(&option), &[&first_taker]).await;
+    assert_program_error(result, Errors::OptionPartiallyFilled);
+
+    // the short takers win: the price settles away from the strike
+    env.advance_clock(600).await;
+    let now = env.now().await;
+    env.set_price(env.price_feed, 20 * 100_000_000, now).await;
+    env.process(env.process_prediction_ix(&option, env.price_feed), &[])
+        .await
+        .unwrap();
+
+    let filled = first_fill + second_fill;
+    let matched_stake = BET_AMOUNT * filled / TAKER_AMOUNT;
+    // the fee is taken from the matched pot only
+    let bet_fees = (matched_stake + filled) * FEE_BPS as u64 / 10_000;
+    let payout = matched_stake + filled - bet_fees;
+    let settled = env.binary_option(option.deposit_account.pubkey()).await;
+    assert_eq!(settled.total_payout, payout);
+
+    let rent = env.context.banks_client.get_rent().await.unwrap();
+    let fill_rent = rent.minimum_balance(8 + Fill::INIT_SPACE);
+    for (taker, fill) in [(&first_taker, first_fill), (&second_taker, second_fill)] {
+        let before = env.balance(taker.pubkey()).await;
+        env.process(env.claim_fill_ix(&option, taker.pubkey()), &[taker])
+            .await
+            .unwrap();
+        let share = (fill as u128 * payout as u128 / filled as u128) as u64;
+        assert_eq!(env.balance(taker.pubkey()).await, before + share + fill_rent);
+    }
+
+    let maker_before = env.balance(maker.pubkey()).await;
+    env.process(env.refund_unfilled_ix(&option), &[&maker]).await.unwrap();
+    assert_eq!(env.balance(maker.pubkey()).await, maker_before + BET_AMOUNT - matched_stake);
+
+    env.advance_clock(0).await;
+    let result = env.process(env.refund_unfilled_ix(&option), &[&maker]).await;
+    assert_program_error(result, Errors::AlreadyWithdrawn);
+}
+
+#[tokio::test]
+async fn create_rejects_each_invalid_parameter_with_its_code() {
+    let mut env = setup().await;
+    let maker = clone_keypair(&env.maker);
+    let now = env.now().await;
+
+    let cases = [
+        (CreateBinaryOptions { bet_description: " ".to_string(), ..create_args() }, Errors::CannotCreateBetting),
+        (CreateBinaryOptions { bet_description: "x".repeat(41), ..create_args() }, Errors::ExceededDescriptionMaxLength),
+        (CreateBinaryOptions { bet_amount: 0, ..create_args() }, Errors::InvalidBetAmount),
+        (CreateBinaryOptions { strike_price: 0, ..create_args() }, Errors::InvalidStrikePrice),
+        (CreateBinaryOptions { taker_amount: 0, ..create_args() }, Errors::InvalidTakerAmount),
+        (
+            CreateBinaryOptions { participant_position: ParticipantPosition::Unknown, ..create_args() },
+            Errors::InvalidPosition,
+        ),
+        (CreateBinaryOptions { referrer: Some(maker.pubkey()), ..create_args() }, Errors::SelfReferral),
+        (CreateBinaryOptions { expiry: now - 1, ..create_args() }, Errors::InvalidExpiry),
+    ];
+
+    for (args, error) in cases {
+        let option = OptionAccounts::new();
+        let instruction = env.create_ix_with(&option, args);
+        let result = env.process(instruction, &[&option.deposit_account, &maker]).await;
+        assert_program_error(result, error);
+    }
+}
+
+#[tokio::test]
+async fn withdrawal_rejects_invalid_amounts_with_their_codes() {
+    let mut env = setup().await;
+    let option = env.open_option().await;
+    let maker = clone_keypair(&env.maker);
+
+    let now = env.now().await;
+    env.set_price(env.price_feed, PYTH_PRICE, now).await;
+
+    env.process(env.process_prediction_ix(&option, env.price_feed), &[])
+        .await
+        .unwrap();
+
+    let result = env.process(env.withdraw_ix(&option, maker.pubkey(), 0), &[&maker]).await;
+    assert_program_error(result, Errors::InvalidWithdrawAmount);
+
+    let result = env.process(env.withdraw_ix(&option, maker.pubkey(), 1), &[&maker]).await;
+    assert_program_error(result, Errors::AmountNotEqualToTotalPayoutAmount);
+}
@@ -13,6 +13,7 @@ describe("binary-options", () => {
   const deposit_account = anchor.web3.Keypair.generate();
   const deposit_auth = anchor.web3.Keypair.generate(); // First participant
   const deposit_auth_2 = anchor.web3.Keypair.generate(); // Second participant
+  const referrer_auth = anchor.web3.Keypair.generate(); // Partner that referred the first participant
   const config = anchor.web3.Keypair.generate();
   const fs = require('fs');
   const assert = require("assert");
@@ -66,6 +67,13 @@ describe("binary-options", () => {
       ],
       program.programId);
 
+  // referrer
+  let [referrer, referrer_bump] = anchor.web3.PublicKey.findProgramAddressSync(
+    [anchor.utils.bytes.utf8.encode("referrer"),
+    referrer_auth.publicKey.toBuffer()
+    ],
+    program.programId);
+
   before(async () => {
 
     let res = await provider.connection.requestAirdrop(admin_auth.publicKey, 100 * anchor.web3.LAMPORTS_PER_SOL);
@@ -108,11 +116,29 @@ describe("binary-options", () => {
 
   });
 
+  before(async () => {
+
+    let res = await provider.connection.requestAirdrop(referrer_auth.publicKey, 1 * anchor.web3.LAMPORTS_PER_SOL);
+
+    let latestBlockHash = await provider.connection.getLatestBlockhash()
+
+    await provider.connection.confirmTransaction({
+      blockhash: latestBlockHash.blockhash,
+      lastValidBlockHeight: latestBlockHash.lastValidBlockHeight,
+      signature: res,
+    });
+
+  });
+
   it("Is initialized!", async () => {
     // Add your test here.
     const tx = await program.methods.initialize(
       {
-        priceFeedId: new anchor.web3.PublicKey(solToUSD)
+        priceFeedId: new anchor.web3.PublicKey(solToUSD),
+        feeBps: 500, // 5% of the pot of each settled option goes to the protocol
+        referralFeeBps: 2000, // 20% of each participant's fees goes to their referrer
+        disputeWindow: new anchor.BN(0), // payouts are claimable as soon as the prediction is processed
+        settlementMode: { spot: {} },
       }
     )
       .accounts({
@@ -130,6 +156,19 @@ describe("binary-options", () => {
     console.log(result);
   });
   
+  it("Register Referrer", async () => {
+    const tx = await program.methods.registerReferrer()
+      .accounts({
+        referrer: referrer,
+        referrerAuth: referrer_auth.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }).signers([referrer_auth]).rpc();
+    console.log("Your transaction signature", tx);
+
+    let result = await program.account.referrer.fetch(referrer);
+    console.log("registerReferrer: ", result);
+  });
+
   it("Create Binary Options", async () => {
     // Add your test here.
     /*
@@ -146,14 +185,18 @@ describe("binary-options", () => {
     let strikePrice = new anchor.BN(25); // SOL price 25
     let takerAmount = new anchor.BN(5 * anchor.web3.LAMPORTS_PER_SOL);
     let participantPosition = { long: {} };
+    let expiry = new anchor.BN(0); // can be settled as soon as it is accepted
 
-    const tx = await program.methods.createBinaryOptions(betDescription, betAmount, strikePrice, takerAmount, participantPosition)
+    const tx = await program.methods.createBinaryOptions(betDescription, betAmount, strikePrice, takerAmount, participantPosition, referrer_auth.publicKey, expiry)
       .accounts({
+        config: config.publicKey,
         depositAccount: deposit_account.publicKey,
         pdaAuth: pda_auth,
         solVault: sol_vault,
         depositAuth: deposit_auth.publicKey,
         adminDepositAccount: admin_deposit_account.publicKey,
+        orderBook: null,
+        referrer: referrer,
         systemProgram: anchor.web3.SystemProgram.programId,
       }).signers([deposit_account, deposit_auth]).rpc();
     console.log("Your transaction signature", tx);
@@ -167,7 +210,7 @@ describe("binary-options", () => {
     let amount = new anchor.BN(5 * anchor.web3.LAMPORTS_PER_SOL);
     let participantPosition = { short: {} };
 
-    const tx = await program.methods.acceptBinaryOptions(amount, participantPosition)
+    const tx = await program.methods.acceptBinaryOptions(amount, participantPosition, null)
       .accounts({
         adminDepositAccount: admin_deposit_account.publicKey,
         adminPdaAuth: admin_pda_auth,
@@ -176,6 +219,8 @@ describe("binary-options", () => {
         pdaAuth: pda_auth,
         solVault: sol_vault,
         depositAuth: deposit_auth_2.publicKey,
+        orderBook: null,
+        referrer: null,
         systemProgram: anchor.web3.SystemProgram.programId,
       }).signers([deposit_auth_2]).rpc();
     console.log("Your transaction signature", tx);
@@ -186,9 +231,7 @@ describe("binary-options", () => {
 
   it("Process Prediction", async () => {
     // Add your test here.
-    let betFees = new anchor.BN(3 * anchor.web3.LAMPORTS_PER_SOL);
-
-    const tx = await program.methods.processPrediction(betFees)
+    const tx = await program.methods.processPrediction()
       .accounts({
         config: config.publicKey,
         pythPriceFeedAccount: new anchor.web3.PublicKey(solToUSD),
@@ -198,6 +241,8 @@ describe("binary-options", () => {
         adminDepositAccount: admin_deposit_account.publicKey,
         adminPdaAuth: admin_pda_auth,
         adminSolVault: admin_sol_vault,
+        makerReferrer: referrer,
+        takerReferrer: null,
         systemProgram: anchor.web3.SystemProgram.programId,
       }).signers([]).rpc();
     console.log("Your transaction signature", tx);
@@ -248,4 +293,17 @@ describe("binary-options", () => {
     console.log("withdraw: ", result);
   });
 
+  it("Claim Referral Fees", async () => {
+    const tx = await program.methods.claimReferralFees()
+      .accounts({
+        referrer: referrer,
+        referrerAuth: referrer_auth.publicKey,
+      }).signers([referrer_auth]).rpc();
+    console.log("Your transaction signature", tx);
+
+    let result = await program.account.referrer.fetch(referrer);
+    console.log("claimReferralFees: ", result);
+    assert(result.accruedFees.eq(result.claimedFees));
+  });
+
 });